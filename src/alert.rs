@@ -0,0 +1,108 @@
+//! Overcurrent alerts sent by legacy three-phase meters in short frames
+
+use crate::{TeleinfoMessage, TeleinfoMode};
+
+/// Overcurrent is the current of a phase exceeding the subscribed current
+/// * phase the phase number, from 1 to 3
+/// * current the current of the phase in A
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Overcurrent {
+    pub phase: u8,
+    pub current: u16,
+}
+
+/// OvercurrentAlert is a short frame a legacy three-phase meter sends between its normal
+/// frames while the current of a phase exceeds the subscribed current
+/// * address the meter address (ADCO)
+/// * overcurrents the phases in overcurrent (ADIR1 to ADIR3)
+/// * currents the instantaneous currents of the three phases in A (IINST1 to IINST3)
+#[derive(Clone, Debug, PartialEq)]
+pub struct OvercurrentAlert {
+    pub address: Option<String>,
+    pub overcurrents: Vec<Overcurrent>,
+    pub currents: [Option<u16>; 3],
+}
+
+impl OvercurrentAlert {
+    /// Return the alert carried by `message`, None if it is not a legacy message with an
+    /// ADIR dataset
+    pub fn from_message(message: &TeleinfoMessage) -> Option<OvercurrentAlert> {
+        if message.mode != TeleinfoMode::Legacy {
+            return None;
+        }
+        let current = |label: String| message.get_current(label).and_then(|c| c.ok());
+        let overcurrents: Vec<Overcurrent> = (1..=3)
+            .filter_map(|phase| {
+                current(format!("ADIR{}", phase)).map(|current| Overcurrent { phase, current })
+            })
+            .collect();
+        if overcurrents.is_empty() {
+            return None;
+        }
+        Some(OvercurrentAlert {
+            address: message
+                .get_value("ADCO".to_string())
+                .map(|v| v.value.clone()),
+            overcurrents,
+            currents: [
+                current("IINST1".to_string()),
+                current("IINST2".to_string()),
+                current("IINST3".to_string()),
+            ],
+        })
+    }
+
+    /// Return the phases in overcurrent, from 1 to 3
+    pub fn get_phases(&self) -> Vec<u8> {
+        self.overcurrents.iter().map(|o| o.phase).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alert::{Overcurrent, OvercurrentAlert};
+    use crate::simulator::fixture::at;
+    use crate::{TeleinfoDecoder, TeleinfoMessageType, TeleinfoMode, TeleinfoSimulator};
+    const SHORT: &[u8] = b"\x02\nADIR2 063 +\r\nADCO 031961098836 M\r\nIINST1 012 K\r\
+                           \nIINST2 063 R\r\nIINST3 008 R\r\x03";
+    #[test]
+    fn test_alert() {
+        let meter =
+            TeleinfoSimulator::new(TeleinfoMode::Legacy, at(2020, 2, 14, 21)).three_phase(true);
+        let mut decoder = TeleinfoDecoder::new();
+        let full = decoder.feed(&meter.frame()).pop().unwrap().unwrap();
+        assert_eq!(OvercurrentAlert::from_message(&full), None);
+        let short = decoder.feed(SHORT).pop().unwrap().unwrap();
+        assert!(short.is_valid());
+        assert_eq!(short.get_message_type(), TeleinfoMessageType::Short);
+        let alert = OvercurrentAlert::from_message(&short).unwrap();
+        assert_eq!(alert.get_phases(), vec![2]);
+        assert_eq!(
+            alert.overcurrents,
+            vec![Overcurrent {
+                phase: 2,
+                current: 63
+            }]
+        );
+        assert_eq!(alert.currents, [Some(12), Some(63), Some(8)]);
+        assert_eq!(alert.address, Some("031961098836".to_string()));
+        // the short frame does not replace the context of the last full frame
+        assert_eq!(decoder.get_last_full_message(), Some(&full));
+        let merged = full.merge(&short);
+        assert_eq!(merged.get_message_type(), TeleinfoMessageType::Normal);
+        assert_eq!(merged.get_value("IINST2".to_string()).unwrap().value, "063");
+        assert_eq!(
+            merged.get_value("OPTARIF".to_string()),
+            full.get_value("OPTARIF".to_string())
+        );
+        assert_eq!(merged.get_overcurrent_alert(), Some(alert));
+    }
+    #[test]
+    fn test_partial_frame() {
+        // a normal frame received without its start and OPTARIF is neither short nor an alert
+        let partial = crate::parse_frame("\nPTEC TH.. $\r\nIINST1 012 K\r").unwrap();
+        assert!(partial.is_valid());
+        assert_eq!(partial.get_message_type(), TeleinfoMessageType::Normal);
+        assert_eq!(partial.get_overcurrent_alert(), None);
+    }
+}
@@ -0,0 +1,160 @@
+//! Billing indices of standard mode messages, named after the supplier tariff periods
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{TariffOption, TariffPeriod, TeleinfoMessage, TeleinfoMode};
+
+/// BillingIndex is an energy index of a standard mode message
+/// * label the label of the index, EASF01 to EASF10 or EASD01 to EASD04
+/// * name the tariff period counted by the index, "HC BLEU" for example, None if unknown
+#[derive(Clone, Debug, PartialEq)]
+pub struct BillingIndex {
+    pub label: String,
+    pub name: Option<String>,
+}
+
+/// Return `name` without its padding and with single spaces between words
+fn normalise(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// IndexHistory learns the supplier indices used by a contract from successive standard mode
+/// messages: the indices of the contract named by NGTF, the index of each LTARF tariff period
+/// given by NTARF and the indices switched to by the PJOURF+1 schedules. A single message
+/// gives the indices of known contracts, unknown contracts need the history of a few days
+/// # Example
+/// ```
+/// let mut history = teleinfo_nom::IndexHistory::new();
+/// let message = teleinfo_nom::parse_frame(concat!(
+///     "\nNGTF\t  HEURES SUPER  \t\\\r\nLTARF\t  SUPER CREUSE  \t!\r\nNTARF\t02\tO\r",
+///     "\nPJOURF+1\t00004002 07004001 NONUTILE\t$\r"))
+///     .unwrap();
+/// assert!(history.push(&message));
+/// let indices = history.get_supplier_indices();
+/// assert_eq!(indices.len(), 2);
+/// assert_eq!(indices[0].label, "EASF01");
+/// assert_eq!(indices[0].name, None);
+/// assert_eq!(indices[1].name, Some("SUPER CREUSE".to_string()));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct IndexHistory {
+    contract: Option<&'static [TariffPeriod]>,
+    names: BTreeMap<u8, String>,
+    used: BTreeSet<u8>,
+}
+
+impl IndexHistory {
+    /// Return an history without any message
+    pub fn new() -> IndexHistory {
+        IndexHistory::default()
+    }
+
+    /// Learn the supplier indices of `message`, return whether it is a standard mode message
+    pub fn push(&mut self, message: &TeleinfoMessage) -> bool {
+        if message.mode != TeleinfoMode::Standard {
+            return false;
+        }
+        if let Some(ngtf) = message.get_value("NGTF".to_string()) {
+            self.contract = TariffOption::from_ngtf(&ngtf.value)
+                .ok()
+                .map(|option| option.periods());
+        }
+        let current = message
+            .get_value("NTARF".to_string())
+            .and_then(|v| v.value.parse::<u8>().ok())
+            .filter(|i| (1..=10).contains(i));
+        if let Some(index) = current {
+            self.used.insert(index);
+            if let Some(ltarf) = message.get_value("LTARF".to_string()) {
+                let name = normalise(&ltarf.value);
+                if !name.is_empty() {
+                    self.names.insert(index, name);
+                }
+            }
+        }
+        if let Some(schedule) = message.get_next_day_schedule() {
+            self.used.extend(
+                schedule
+                    .slots
+                    .iter()
+                    .filter_map(|s| s.index)
+                    .filter(|i| *i <= 10),
+            );
+        }
+        true
+    }
+
+    /// Return the supplier indices used by the contract, ordered by index, named after the
+    /// LTARF labels seen or, for a known contract, after its tariff periods
+    pub fn get_supplier_indices(&self) -> Vec<BillingIndex> {
+        let mut indices = self.used.clone();
+        let contract = self.contract.unwrap_or(&[]);
+        indices.extend(contract.iter().map(|period| period.supplier));
+        indices
+            .into_iter()
+            .map(|index| BillingIndex {
+                label: format!("EASF{:02}", index),
+                name: self.names.get(&index).cloned().or_else(|| {
+                    contract
+                        .iter()
+                        .find(|period| period.supplier == index)
+                        .map(|period| period.name.to_string())
+                }),
+            })
+            .collect()
+    }
+}
+
+/// Return the distributor indices of `message`. Distributor tariff periods are not sent by
+/// the meter and the calendar of the distributor is unknown, so the indices are not named
+pub(crate) fn distributor_indices(message: &TeleinfoMessage) -> Vec<BillingIndex> {
+    (1..=4)
+        .map(|i| format!("EASD{:02}", i))
+        .filter(|label| message.get_value(label.clone()).is_some())
+        .map(|label| BillingIndex { label, name: None })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::billing::*;
+    use crate::simulator::fixture::{at, decode};
+    use crate::{Contract, TeleinfoSimulator};
+    use std::time::Duration;
+    #[test]
+    fn test_normalise() {
+        assert_eq!(normalise("   HC  BLANC    "), "HC BLANC");
+        assert_eq!(normalise("                "), "");
+    }
+    #[test]
+    fn test_simulator_indices() {
+        let mut meter = TeleinfoSimulator::new(TeleinfoMode::Standard, at(2020, 2, 14, 12))
+            .contract(Contract::OffPeak);
+        let mut history = IndexHistory::new();
+        // a day of frames names both indices after their LTARF labels
+        for _ in 0..24 {
+            let message = decode(&meter);
+            assert!(history.push(&message));
+            meter.advance(Duration::from_secs(3600));
+        }
+        let indices = history.get_supplier_indices();
+        assert_eq!(
+            indices,
+            vec![
+                BillingIndex {
+                    label: "EASF01".to_string(),
+                    name: Some("HEURE CREUSE".to_string()),
+                },
+                BillingIndex {
+                    label: "EASF02".to_string(),
+                    name: Some("HEURE PLEINE".to_string()),
+                },
+            ]
+        );
+        let message = decode(&meter);
+        let distributor = message.get_distributor_indices();
+        assert_eq!(distributor.len(), 4);
+        assert!(distributor.iter().all(|index| index.name.is_none()));
+        assert!(!history.push(&crate::parse_frame("\nADCO 031961098836 M\r").unwrap()));
+    }
+}
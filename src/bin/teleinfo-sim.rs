@@ -0,0 +1,285 @@
+//! teleinfo-sim writes the teleinfo stream of a simulated meter to stdout, a file or a
+//! pseudo-terminal, paced at the baud rate of the teleinfo output
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
+use teleinfo_nom::{
+    Contract, LoadProfile, TariffCalendar, TeleinfoMode, TeleinfoSimulator, TempoColor,
+};
+
+const USAGE: &str = "Usage: teleinfo-sim [OPTIONS]
+
+Options:
+  --mode legacy|standard        teleinfo mode (standard)
+  --contract base|hc|ejp|tempo  tariff option (base)
+  --three-phase                 simulate a three-phase meter
+  --power KVA                   subscribed power (9)
+  --load VA,VA,...              consumption profile, values spread over the day
+  --producer VA                 peak power of simulated solar panels
+  --offpeak HH:MM-HH:MM,...     off-peak hours (22:00-06:00)
+  --white DATE,...              white Tempo days
+  --red DATE,...                red Tempo days
+  --ejp DATE,...                EJP peak days
+  --start YYYY-MM-DDTHH:MM:SS   start time of the meter clock (now)
+  --speed N                     simulated seconds per second (1)
+  --baud N                      baud rate (1200 in legacy mode, 9600 in standard mode)
+  --frames N                    stop after N frames
+  --output PATH                 write to PATH instead of stdout
+  --pty                         write to a new pseudo-terminal and print its path";
+
+struct Options {
+    meter: TeleinfoSimulator,
+    speed: f64,
+    baud: u32,
+    frames: Option<u64>,
+    output: Output,
+}
+
+enum Output {
+    Stdout,
+    File(String),
+    Pty,
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("teleinfo-sim: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    let output: io::Result<Box<dyn Write>> = match options.output {
+        Output::Stdout => Ok(Box::new(io::stdout())),
+        Output::File(ref path) => File::create(path).map(|f| Box::new(f) as Box<dyn Write>),
+        Output::Pty => pty::open(options.baud).map(|(master, path)| {
+            eprintln!("teleinfo-sim: writing to {}", path);
+            Box::new(master) as Box<dyn Write>
+        }),
+    };
+    let result = output.and_then(|output| {
+        run(
+            options.meter,
+            options.speed,
+            options.baud,
+            options.frames,
+            output,
+        )
+    });
+    match result {
+        Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => (),
+        Err(e) => {
+            eprintln!("teleinfo-sim: {}", e);
+            process::exit(1);
+        }
+        Ok(()) => (),
+    }
+}
+
+/// Write the frames of `meter` to `output`, each character taking the time of its
+/// 10 bits (start, 7 data, parity and stop bits) at `baud`
+fn run<W: Write>(
+    mut meter: TeleinfoSimulator,
+    speed: f64,
+    baud: u32,
+    frames: Option<u64>,
+    mut output: W,
+) -> io::Result<()> {
+    let character = Duration::from_secs(10) / baud;
+    let mut sent = 0;
+    while !matches!(frames, Some(f) if sent >= f) {
+        let start = Instant::now();
+        let frame = meter.frame();
+        for (i, chunk) in frame.chunks(16).enumerate() {
+            output.write_all(chunk)?;
+            output.flush()?;
+            let deadline = start + character * (16 * i + chunk.len()) as u32;
+            if let Some(wait) = deadline.checked_duration_since(Instant::now()) {
+                thread::sleep(wait);
+            }
+        }
+        meter.advance(start.elapsed().mul_f64(speed));
+        sent += 1;
+    }
+    Ok(())
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut mode = TeleinfoMode::Standard;
+    let mut contract = Contract::Base;
+    let mut three_phase = false;
+    let mut power = 9;
+    let mut load = LoadProfile::default();
+    let mut production = None;
+    let mut calendar = TariffCalendar::new();
+    let mut start = Local::now().naive_local();
+    let mut speed = 1.0;
+    let mut baud = None;
+    let mut frames = None;
+    let mut output = Output::Stdout;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--mode" => {
+                mode = match value()?.as_str() {
+                    "legacy" => TeleinfoMode::Legacy,
+                    "standard" => TeleinfoMode::Standard,
+                    m => return Err(format!("unknown mode {}", m)),
+                }
+            }
+            "--contract" => {
+                contract = match value()?.as_str() {
+                    "base" => Contract::Base,
+                    "hc" => Contract::OffPeak,
+                    "ejp" => Contract::Ejp,
+                    "tempo" => Contract::Tempo,
+                    c => return Err(format!("unknown contract {}", c)),
+                }
+            }
+            "--three-phase" => three_phase = true,
+            "--power" => power = number(&value()?)?,
+            "--load" => load = LoadProfile::new(list(&value()?, number)?),
+            "--producer" => production = Some(LoadProfile::solar(number(&value()?)?)),
+            "--offpeak" => calendar = calendar.off_peak(list(&value()?, period)?),
+            "--white" => {
+                for day in list(&value()?, date)? {
+                    calendar = calendar.color(day, TempoColor::White);
+                }
+            }
+            "--red" => {
+                for day in list(&value()?, date)? {
+                    calendar = calendar.color(day, TempoColor::Red);
+                }
+            }
+            "--ejp" => {
+                for day in list(&value()?, date)? {
+                    calendar = calendar.peak_day(day);
+                }
+            }
+            "--start" => {
+                let v = value()?;
+                start = NaiveDateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S")
+                    .map_err(|_| format!("invalid start time {}", v))?
+            }
+            "--speed" => {
+                let v = value()?;
+                speed = v
+                    .parse()
+                    .ok()
+                    .filter(|s: &f64| *s >= 0.0)
+                    .ok_or(format!("invalid speed {}", v))?
+            }
+            "--baud" => baud = Some(number(&value()?)?).filter(|b| *b > 0),
+            "--frames" => frames = Some(number(&value()?)? as u64),
+            "--output" => output = Output::File(value()?),
+            "--pty" => output = Output::Pty,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            a => return Err(format!("unknown argument {}", a)),
+        }
+    }
+    let mut meter = TeleinfoSimulator::new(mode, start)
+        .contract(contract)
+        .three_phase(three_phase)
+        .subscribed(power)
+        .load(load)
+        .calendar(calendar);
+    if let Some(production) = production {
+        meter = meter.production(production);
+    }
+    let baud = baud.unwrap_or(teleinfo_nom::baud_rate(mode));
+    Ok(Options {
+        meter,
+        speed,
+        baud,
+        frames,
+        output,
+    })
+}
+
+fn number(value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number {}", value))
+}
+
+fn date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("invalid date {}", value))
+}
+
+fn period(value: &str) -> Result<(NaiveTime, NaiveTime), String> {
+    let time =
+        |t| NaiveTime::parse_from_str(t, "%H:%M").map_err(|_| format!("invalid period {}", value));
+    let mut bounds = value.splitn(2, '-');
+    match (bounds.next(), bounds.next()) {
+        (Some(start), Some(end)) => Ok((time(start)?, time(end)?)),
+        _ => Err(format!("invalid period {}", value)),
+    }
+}
+
+fn list<T>(value: &str, parse: fn(&str) -> Result<T, String>) -> Result<Vec<T>, String> {
+    value.split(',').map(parse).collect()
+}
+
+#[cfg(target_os = "linux")]
+mod pty {
+    use std::ffi::CStr;
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::FromRawFd;
+
+    /// Open a pseudo-terminal in raw mode at `baud` and return its master side and the
+    /// path of its slave side, to be opened like a serial port
+    pub fn open(baud: u32) -> io::Result<(File, String)> {
+        unsafe {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let master = File::from_raw_fd(fd);
+            if libc::grantpt(fd) != 0 || libc::unlockpt(fd) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut name = [0 as libc::c_char; 128];
+            if libc::ptsname_r(fd, name.as_mut_ptr(), name.len()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let path = CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned();
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(fd, &mut termios) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            libc::cfmakeraw(&mut termios);
+            let speed = match baud {
+                1200 => libc::B1200,
+                _ => libc::B9600,
+            };
+            if libc::cfsetspeed(&mut termios, speed) != 0
+                || libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            Ok((master, path))
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod pty {
+    use std::fs::File;
+    use std::io;
+
+    pub fn open(_baud: u32) -> io::Result<(File, String)> {
+        Err(io::Error::other(
+            "pseudo-terminals are only supported on Linux",
+        ))
+    }
+}
@@ -0,0 +1,148 @@
+//! tokio-util codec of teleinfo messages, enabled by the `codec` feature
+
+use std::collections::VecDeque;
+use std::io;
+
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{encode_message, TeleinfoDecoder, TeleinfoError, TeleinfoMessage};
+
+/// TeleinfoCodec decodes the STX/ETX frames of a stream into `TeleinfoMessage` with a
+/// `TeleinfoDecoder` and encodes messages to their wire format, to be used with `FramedRead`
+/// and `FramedWrite`.
+/// Each decoded item is the `Result<TeleinfoMessage, TeleinfoError>` of a frame: a
+/// `FramedRead` ends at the first codec error, so a malformed frame is an `Err` item and
+/// decoding goes on with the next frame, the `io::Error` of the codec being the I/O errors of
+/// the stream. A frame left unfinished at the end of the stream is a framing error item
+/// # Example
+/// ```
+/// use futures_util::StreamExt;
+/// use tokio_util::codec::FramedRead;
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let data: &[u8] = b"\x02\nADCO 031961098836 M\r\nPAPP 00120 $\r\x03";
+/// let mut framed = FramedRead::new(data, teleinfo_nom::TeleinfoCodec::new());
+/// let message = framed.next().await.unwrap().unwrap().unwrap();
+/// assert_eq!(message.get_value("PAPP".to_string()).unwrap().value, "00120");
+/// assert!(framed.next().await.is_none());
+/// # });
+/// ```
+#[derive(Debug, Default)]
+pub struct TeleinfoCodec {
+    decoder: TeleinfoDecoder,
+    pending: VecDeque<Result<TeleinfoMessage, TeleinfoError>>,
+}
+
+impl TeleinfoCodec {
+    /// Return a codec with a default `TeleinfoDecoder`
+    pub fn new() -> TeleinfoCodec {
+        TeleinfoCodec {
+            decoder: TeleinfoDecoder::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Use `decoder` to decode the messages
+    pub fn decoder(mut self, decoder: TeleinfoDecoder) -> TeleinfoCodec {
+        self.decoder = decoder;
+        self
+    }
+
+    /// Return the decoder used for the messages
+    pub fn get_decoder(&self) -> &TeleinfoDecoder {
+        &self.decoder
+    }
+}
+
+impl Decoder for TeleinfoCodec {
+    type Item = Result<TeleinfoMessage, TeleinfoError>;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, io::Error> {
+        if !src.is_empty() {
+            let data = src.split();
+            self.pending.extend(self.decoder.feed(&data));
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, io::Error> {
+        match self.decode(src)? {
+            Some(item) => Ok(Some(item)),
+            None => Ok(self.decoder.finish().map(Err)),
+        }
+    }
+}
+
+impl Encoder<TeleinfoMessage> for TeleinfoCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: TeleinfoMessage, dst: &mut BytesMut) -> Result<(), io::Error> {
+        dst.extend_from_slice(&encode_message(&item));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::TeleinfoCodec;
+    use crate::TeleinfoError;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_util::codec::{FramedRead, FramedWrite};
+    #[tokio::test]
+    async fn test_framed() {
+        let data = include_bytes!("../assets/stream_standard_raw.txt");
+        let mut items: Vec<_> = FramedRead::new(&data[..], TeleinfoCodec::new())
+            .map(|m| m.unwrap())
+            .collect()
+            .await;
+        // the sample ends with the start of a frame
+        assert_eq!(items.len(), 8);
+        assert!(matches!(
+            items.pop(),
+            Some(Err(TeleinfoError::Framing { .. }))
+        ));
+        let messages: Vec<_> = items.into_iter().map(|m| m.unwrap()).collect();
+        let mut written = Vec::new();
+        let mut sink = FramedWrite::new(&mut written, TeleinfoCodec::new());
+        for message in messages.iter().filter(|m| m.is_valid()) {
+            sink.send(message.clone()).await.unwrap();
+        }
+        let decoded: Vec<_> = FramedRead::new(&written[..], TeleinfoCodec::new())
+            .map(|m| m.unwrap().unwrap())
+            .collect()
+            .await;
+        let valid: Vec<_> = messages.into_iter().filter(|m| m.is_valid()).collect();
+        assert_eq!(decoded, valid);
+    }
+    #[tokio::test]
+    async fn test_framed_error() {
+        // an interrupted frame is reported and decoding goes on with the next one
+        let data: &[u8] = b"\x02\nADCO 031961098836 M\r\nOPT\x04\x02\nADCO 031961098836 M\r\x03";
+        let items: Vec<_> = FramedRead::new(data, TeleinfoCodec::new())
+            .map(|m| m.unwrap())
+            .collect()
+            .await;
+        assert_eq!(items.len(), 2);
+        assert!(matches!(items[0], Err(TeleinfoError::Interrupted { .. })));
+        assert!(items[1].as_ref().unwrap().is_valid());
+    }
+    #[tokio::test]
+    async fn test_framed_eof() {
+        // a frame cut by the end of the stream is reported
+        let data: &[u8] = b"\x02\nADCO 031961098836 M\r\x03\x02\nADCO 0319";
+        let items: Vec<_> = FramedRead::new(data, TeleinfoCodec::new())
+            .map(|m| m.unwrap())
+            .collect()
+            .await;
+        assert_eq!(items.len(), 2);
+        assert!(items[0].as_ref().unwrap().is_valid());
+        match &items[1] {
+            Err(TeleinfoError::Framing { offset, line }) => {
+                assert_eq!(*offset, 23);
+                assert_eq!(line, "ADCO 0319");
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
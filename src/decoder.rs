@@ -0,0 +1,382 @@
+//! Push based decoding of a teleinfo stream
+
+use crate::parser::{self, Frame};
+use crate::{TeleinfoError, TeleinfoMessage, TeleinfoMessageType, TeleinfoParser};
+
+const STX: u8 = 0x02;
+const ETX: u8 = 0x03;
+const EOT: u8 = 0x04;
+
+/// TeleinfoDecoder accumulates bytes of a teleinfo stream and returns the messages they
+/// complete. Each byte is scanned once and the buffer is bounded by its capacity: when no
+/// message ends within it, the oldest data is dropped and reported as a framing error.
+/// A message is read from the last STX before its ETX, the data of a truncated message before
+/// it is reported as a framing error. A message aborted by an EOT character is reported as
+/// interrupted and decoding resumes at the next STX
+#[derive(Clone, Debug)]
+pub struct TeleinfoDecoder {
+    buffer: Vec<u8>,
+    scanned: usize,
+    position: usize,
+    capacity: usize,
+    parser: TeleinfoParser,
+    parity: bool,
+    parity_errors: usize,
+    parity_positions: Vec<usize>,
+    last_full: Option<TeleinfoMessage>,
+}
+
+impl Default for TeleinfoDecoder {
+    fn default() -> Self {
+        TeleinfoDecoder::new()
+    }
+}
+
+impl TeleinfoDecoder {
+    /// Return a decoder with a default `TeleinfoParser` and a capacity of 4096 bytes
+    pub fn new() -> TeleinfoDecoder {
+        TeleinfoDecoder {
+            buffer: Vec::with_capacity(4096),
+            scanned: 0,
+            position: 0,
+            capacity: 4096,
+            parser: TeleinfoParser::new(),
+            parity: false,
+            parity_errors: 0,
+            parity_positions: Vec::new(),
+            last_full: None,
+        }
+    }
+
+    /// Use `parser` to parse the messages
+    pub fn parser(mut self, parser: TeleinfoParser) -> TeleinfoDecoder {
+        self.parser = parser;
+        self
+    }
+
+    /// Keep at most `capacity` bytes of an unfinished message
+    pub fn capacity(mut self, capacity: usize) -> TeleinfoDecoder {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Check and strip the even parity bit of bytes read as 8 data bits from a 7E1 line.
+    /// Datasets holding a byte with a parity error are flagged in their message
+    pub fn parity(mut self, parity: bool) -> TeleinfoDecoder {
+        self.parity = parity;
+        self
+    }
+
+    /// Return the number of bytes received with a parity error
+    pub fn get_parity_errors(&self) -> usize {
+        self.parity_errors
+    }
+
+    /// Return the parser used for the messages
+    pub fn get_parser(&self) -> &TeleinfoParser {
+        &self.parser
+    }
+
+    /// Return the last valid normal message, the context of the short frames received since
+    pub fn get_last_full_message(&self) -> Option<&TeleinfoMessage> {
+        self.last_full.as_ref()
+    }
+
+    /// Return the bytes received and not yet part of a returned message
+    pub fn get_pending(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Add `data` to the decoder and return the messages it completes, in stream order
+    /// # Example
+    /// ```
+    /// let data = std::fs::read("assets/stream_standard_raw.txt").unwrap();
+    /// let mut decoder = teleinfo_nom::TeleinfoDecoder::new();
+    /// let mut messages = Vec::new();
+    /// for chunk in data.chunks(64) {
+    ///     messages.extend(decoder.feed(chunk));
+    /// }
+    /// assert_eq!(messages.len(), 7);
+    /// assert!(messages[0].as_ref().unwrap().is_valid());
+    /// ```
+    pub fn feed(&mut self, data: &[u8]) -> Vec<Result<TeleinfoMessage, TeleinfoError>> {
+        if self.parity {
+            for &byte in data {
+                if byte.count_ones() % 2 == 1 {
+                    self.parity_errors += 1;
+                    self.parity_positions.push(self.buffer.len());
+                }
+                self.buffer.push(byte & 0x7F);
+            }
+        } else {
+            self.buffer.extend_from_slice(data);
+        }
+        let mut results = Vec::new();
+        while let Some(p) = self.buffer[self.scanned..]
+            .iter()
+            .position(|&b| b == ETX || b == EOT)
+        {
+            let mut end = self.scanned + p + 1;
+            // a STX within the message starts a new one, the data before it is dropped
+            if let Some(stx) = self.buffer[..end].iter().rposition(|&b| b == STX) {
+                if stx > 0 {
+                    results.push(Err(self.framing(stx)));
+                    end -= stx;
+                }
+            }
+            let data = String::from_utf8_lossy(&self.buffer[..end]);
+            if let Ok((_, Frame::Interrupted(partial))) = parser::get_frame(&data) {
+                let stx = self.buffer.iter().position(|&b| b == STX).unwrap_or(0);
+                results.push(Err(TeleinfoError::Interrupted {
+                    offset: self.position + stx,
+                    partial: partial.to_string(),
+                }));
+            } else if let Ok((_, frame)) = parser::get_message(&data) {
+                let result = self.parser.parse(frame).map(|mut message| {
+                    // the frame ends before the ETX and is ASCII when the parity is stripped
+                    let start = data.len() - 1 - frame.len();
+                    let errors: Vec<usize> = self
+                        .parity_positions
+                        .iter()
+                        .copied()
+                        .filter(|&p| p + 1 >= start && p < end)
+                        .collect();
+                    if !errors.is_empty() {
+                        // errors on the STX and ETX invalidate the message only
+                        let offsets: Vec<usize> = errors
+                            .iter()
+                            .filter(|&&p| p >= start)
+                            .map(|p| p - start)
+                            .collect();
+                        message.flag_parity_errors(&parity_labels(frame, &offsets));
+                    }
+                    message
+                });
+                if let Ok(message) = &result {
+                    if message.is_valid()
+                        && message.get_message_type() == TeleinfoMessageType::Normal
+                    {
+                        self.last_full = Some(message.clone());
+                    }
+                }
+                results.push(result);
+            }
+            self.consume(end);
+        }
+        self.scanned = self.buffer.len();
+        if self.buffer.len() > self.capacity {
+            results.push(Err(self.discard()));
+        }
+        results
+    }
+
+    /// Drop the data pending at the end of the stream, return the framing error reporting it
+    pub fn finish(&mut self) -> Option<TeleinfoError> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        Some(self.framing(self.buffer.len()))
+    }
+
+    fn consume(&mut self, count: usize) {
+        self.buffer.drain(..count);
+        self.parity_positions.retain(|&p| p >= count);
+        for p in self.parity_positions.iter_mut() {
+            *p -= count;
+        }
+        self.position += count;
+        self.scanned = 0;
+    }
+
+    fn discard(&mut self) -> TeleinfoError {
+        let keep = match self.buffer.iter().rposition(|&b| b == STX) {
+            Some(start) if self.buffer.len() - start <= self.capacity && start > 0 => start,
+            _ => self.buffer.len(),
+        };
+        let error = self.framing(keep);
+        self.scanned = self.buffer.len();
+        error
+    }
+
+    /// Drop the first `count` bytes of the buffer, return the framing error reporting them
+    fn framing(&mut self, count: usize) -> TeleinfoError {
+        let data = String::from_utf8_lossy(&self.buffer[..count]).into_owned();
+        let offset = self.position;
+        self.consume(count);
+        let line = data
+            .trim_start_matches(['\u{02}', '\n'])
+            .split(['\r', '\n'])
+            .next()
+            .unwrap_or("");
+        TeleinfoError::Framing {
+            offset,
+            line: line.to_string(),
+        }
+    }
+}
+
+/// Return the labels of the datasets of `frame` holding the bytes at `offsets`
+fn parity_labels(frame: &str, offsets: &[usize]) -> Vec<String> {
+    parser::split_datasets(frame)
+        .into_iter()
+        .filter(|(start, dataset)| {
+            offsets
+                .iter()
+                .any(|o| o >= start && *o < start + dataset.len())
+        })
+        .filter_map(|(_, dataset)| {
+            dataset
+                .trim_start_matches('\n')
+                .split([' ', '\t'])
+                .next()
+                .map(|label| label.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decoder::TeleinfoDecoder;
+    use crate::TeleinfoError;
+    #[test]
+    fn test_feed_chunks() {
+        let data = include_bytes!("../assets/stream_standard_raw.txt");
+        let expected: Vec<_> = TeleinfoDecoder::new()
+            .feed(data)
+            .into_iter()
+            .map(|m| m.unwrap())
+            .collect();
+        assert_eq!(expected.len(), 7);
+        for size in [1, 7, 200, 1000].iter() {
+            let mut decoder = TeleinfoDecoder::new();
+            let messages: Vec<_> = data
+                .chunks(*size)
+                .flat_map(|c| decoder.feed(c))
+                .map(|m| m.unwrap())
+                .collect();
+            assert_eq!(messages, expected);
+            assert_eq!(decoder.get_pending(), &data[data.len() - 1155..]);
+        }
+    }
+    #[test]
+    fn test_capacity() {
+        let mut decoder = TeleinfoDecoder::new().capacity(64);
+        assert!(decoder.feed(&[b'x'; 60]).is_empty());
+        match decoder.feed(&[b'x'; 10]).pop() {
+            Some(Err(TeleinfoError::Framing { offset, .. })) => assert_eq!(offset, 0),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(decoder.get_pending().is_empty());
+        let mut garbage = vec![b'x'; 60];
+        garbage.extend_from_slice(b"\x02\nADCO 031961098836 M\r");
+        match decoder.feed(&garbage).pop() {
+            Some(Err(TeleinfoError::Framing { offset, .. })) => assert_eq!(offset, 70),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!(decoder.get_pending(), b"\x02\nADCO 031961098836 M\r");
+        let messages = decoder.feed(b"\nPAPP 00120 $\r\x03");
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].as_ref().unwrap().is_valid());
+    }
+    #[test]
+    fn test_interrupted() {
+        let data = include_bytes!("../assets/stream_legacy_raw.txt");
+        let expected: Vec<_> = TeleinfoDecoder::new()
+            .feed(data)
+            .into_iter()
+            .map(|m| m.unwrap())
+            .collect();
+        // the meter aborts the second message in the middle of a dataset
+        let first = data.iter().position(|&b| b == 0x02).unwrap();
+        let second = first + 1 + data[first + 1..].iter().position(|&b| b == 0x02).unwrap();
+        let mut line = data[..second + 40].to_vec();
+        line.push(0x04);
+        line.extend_from_slice(&data[second..]);
+        let mut decoder = TeleinfoDecoder::new();
+        let results: Vec<_> = line.chunks(30).flat_map(|c| decoder.feed(c)).collect();
+        assert_eq!(results.len(), expected.len() + 1);
+        match &results[1] {
+            Err(TeleinfoError::Interrupted { offset, partial }) => {
+                assert_eq!(*offset, second);
+                assert_eq!(partial.as_bytes(), &data[second + 1..second + 40]);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        let messages: Vec<_> = results.into_iter().filter_map(|m| m.ok()).collect();
+        assert_eq!(messages, expected);
+    }
+    #[test]
+    fn test_truncated() {
+        let data = include_bytes!("../assets/stream_standard_raw.txt");
+        let expected: Vec<_> = TeleinfoDecoder::new()
+            .feed(data)
+            .into_iter()
+            .map(|m| m.unwrap())
+            .collect();
+        // the first message is cut after 20 bytes, the next one starts without its ETX
+        let first = data.iter().position(|&b| b == 0x02).unwrap();
+        let second = first + 1 + data[first + 1..].iter().position(|&b| b == 0x02).unwrap();
+        let mut line = data[..first + 20].to_vec();
+        line.extend_from_slice(&data[second..]);
+        for size in [1, 30, 1000].iter() {
+            let mut decoder = TeleinfoDecoder::new();
+            let results: Vec<_> = line.chunks(*size).flat_map(|c| decoder.feed(c)).collect();
+            assert_eq!(results.len(), expected.len());
+            match &results[0] {
+                Err(TeleinfoError::Framing { offset, line }) => {
+                    assert_eq!(*offset, first);
+                    assert!(line.starts_with("ADSC"));
+                }
+                r => panic!("unexpected result {:?}", r),
+            }
+            let messages: Vec<_> = results.into_iter().filter_map(|m| m.ok()).collect();
+            assert_eq!(messages, &expected[1..]);
+        }
+    }
+    #[test]
+    fn test_parity() {
+        let data = include_bytes!("../assets/stream_standard_raw.txt");
+        let expected: Vec<_> = TeleinfoDecoder::new()
+            .feed(data)
+            .into_iter()
+            .map(|m| m.unwrap())
+            .collect();
+        let mut line: Vec<u8> = data
+            .iter()
+            .map(|b| b | ((b.count_ones() % 2) as u8) << 7)
+            .collect();
+        let mut decoder = TeleinfoDecoder::new().parity(true);
+        let messages: Vec<_> = decoder
+            .feed(&line)
+            .into_iter()
+            .map(|m| m.unwrap())
+            .collect();
+        assert_eq!(messages, expected);
+        assert_eq!(decoder.get_parity_errors(), 0);
+        // parity errors in the value of EASF02 of the first message and in the STX of the
+        // last one, the only valid messages
+        let first = data.iter().position(|&b| b == 0x02).unwrap();
+        let easf02 = first
+            + data[first..]
+                .windows(6)
+                .position(|w| w == b"EASF02")
+                .unwrap();
+        line[easf02 + 9] ^= 0x80;
+        let pending = data.iter().rposition(|&b| b == 0x02).unwrap();
+        line[data[..pending].iter().rposition(|&b| b == 0x02).unwrap()] ^= 0x80;
+        let mut decoder = TeleinfoDecoder::new().parity(true);
+        let messages: Vec<_> = decoder
+            .feed(&line)
+            .into_iter()
+            .map(|m| m.unwrap())
+            .collect();
+        assert_eq!(decoder.get_parity_errors(), 2);
+        assert_eq!(messages.len(), 7);
+        assert_eq!(
+            messages[0].get_parity_error_labels(),
+            vec!["EASF02".to_string()]
+        );
+        assert!(messages[6].get_parity_error_labels().is_empty());
+        assert!(messages.iter().all(|m| !m.is_valid()));
+    }
+}
@@ -0,0 +1,284 @@
+//! Detection of the mode and baud rate of a teleinfo line from a raw sample
+
+use std::fmt;
+
+use crate::parser::{self, ChecksumMethod, LabelKind};
+use crate::TeleinfoMode;
+
+const STX: u8 = 0x02;
+const ETX: u8 = 0x03;
+
+/// Recommendation tells how to configure the serial port after a detection
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Recommendation {
+    /// the sample is readable, keep the port settings
+    Keep,
+    /// the sample is readable once the parity bit read as 8th data bit is stripped, keep the
+    /// port settings and enable `TeleinfoDecoder::parity`
+    StripParity,
+    /// the sample looks read at a wrong speed, reopen the port at this baud rate
+    Reopen(u32),
+    /// the sample is too short or too ambiguous, read a longer one
+    ReadMore,
+}
+
+impl fmt::Display for Recommendation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Recommendation::Keep => write!(f, "keep the port settings"),
+            Recommendation::StripParity => write!(
+                f,
+                "keep the port settings and strip the parity bit, or open the port as 7E1"
+            ),
+            Recommendation::Reopen(baud_rate) => {
+                write!(f, "reopen the port at {} baud, 7E1", baud_rate)
+            }
+            Recommendation::ReadMore => write!(f, "read a longer sample"),
+        }
+    }
+}
+
+/// Detection is the result of the analysis of a raw sample of a teleinfo line
+/// * mode the detected mode, None if it could not be determined
+/// * baud_rate the likely baud rate of the line, 1200 in legacy mode and 9600 in standard mode
+/// * confidence the confidence in the detection, from 0 to 1
+/// * recommendation how to configure the serial port
+#[derive(Clone, Debug, PartialEq)]
+pub struct Detection {
+    pub mode: Option<TeleinfoMode>,
+    pub baud_rate: Option<u32>,
+    pub confidence: f64,
+    pub recommendation: Recommendation,
+}
+
+/// Return the mode and baud rate of the line `sample` was read from, with the port opened at
+/// any speed and as 7E1 or 8N1.
+/// A readable sample is analysed through its datasets with a valid checksum, its separators
+/// and the spacing of its STX and ETX characters. An unreadable sample is analysed through
+/// the bit transitions of its bytes: a line read too fast gives bytes made of long runs of
+/// identical bits, a line read too slow gives bytes looking random
+/// # Example
+/// ```
+/// use teleinfo_nom::{detect, Recommendation, TeleinfoMode};
+/// let sample = std::fs::read("assets/stream_legacy_raw.txt").unwrap();
+/// let detection = detect(&sample);
+/// assert_eq!(detection.mode, Some(TeleinfoMode::Legacy));
+/// assert_eq!(detection.baud_rate, Some(1200));
+/// assert_eq!(detection.recommendation, Recommendation::Keep);
+/// assert!(detection.confidence > 0.9);
+/// ```
+pub fn detect(sample: &[u8]) -> Detection {
+    let stripped: Vec<u8> = sample.iter().map(|b| b & 0x7F).collect();
+    let text = String::from_utf8_lossy(&stripped);
+    let lines = dataset_candidates(&text);
+    let valid = |mode| {
+        lines
+            .iter()
+            .filter(|line| is_valid_dataset(mode, line))
+            .count()
+    };
+    let (legacy, standard) = (valid(TeleinfoMode::Legacy), valid(TeleinfoMode::Standard));
+    if legacy.max(standard) >= 3 {
+        let (mode, count) = match legacy >= standard {
+            true => (TeleinfoMode::Legacy, legacy),
+            false => (TeleinfoMode::Standard, standard),
+        };
+        let mut confidence = 0.6 * count as f64 / lines.len() as f64;
+        if !matches!(separator_mode(&lines), Some(m) if m != mode) {
+            confidence += 0.2;
+        }
+        if !matches!(frame_mode(&stripped), Some(m) if m != mode) {
+            confidence += 0.2;
+        }
+        let recommendation = match has_parity_bit(sample) {
+            true => Recommendation::StripParity,
+            false => Recommendation::Keep,
+        };
+        return Detection {
+            mode: Some(mode),
+            baud_rate: Some(baud_rate(mode)),
+            confidence,
+            recommendation,
+        };
+    }
+    wrong_speed(sample)
+}
+
+/// Return the baud rate the specification defines for `mode`
+pub fn baud_rate(mode: TeleinfoMode) -> u32 {
+    match mode {
+        TeleinfoMode::Legacy => 1200,
+        TeleinfoMode::Standard => 9600,
+    }
+}
+
+fn dataset_candidates(text: &str) -> Vec<String> {
+    text.split('\n')
+        .skip(1)
+        .filter_map(|line| line.split('\r').next())
+        .filter(|line| !line.is_empty() && line.len() < 200)
+        .map(|line| format!("\n{}\r", line))
+        .collect()
+}
+
+fn is_valid_dataset(mode: TeleinfoMode, line: &str) -> bool {
+    match parser::parser_dataset(mode, line) {
+        Ok(("", tuple)) => {
+            parser::label_kind(mode, tuple.0) != LabelKind::Unknown
+                && (parser::validate_with(ChecksumMethod::WithSeparator, mode, &tuple)
+                    || parser::validate_with(ChecksumMethod::WithoutSeparator, mode, &tuple))
+        }
+        _ => false,
+    }
+}
+
+/// Return the mode of the separators of `lines`, tabs being only used in standard mode
+fn separator_mode(lines: &[String]) -> Option<TeleinfoMode> {
+    let tabs = lines.iter().filter(|l| l.contains('\t')).count();
+    let spaces = lines
+        .iter()
+        .filter(|l| !l.contains('\t') && l.contains(' '))
+        .count();
+    match (tabs, spaces) {
+        (0, 0) => None,
+        (t, s) if t >= s => Some(TeleinfoMode::Standard),
+        _ => Some(TeleinfoMode::Legacy),
+    }
+}
+
+/// Return the mode matching the average length of the frames of `data`: legacy frames are
+/// a few hundred bytes long, standard frames around a thousand
+fn frame_mode(data: &[u8]) -> Option<TeleinfoMode> {
+    let mut lengths = Vec::new();
+    let mut start = None;
+    for (i, &b) in data.iter().enumerate() {
+        match b {
+            STX => start = Some(i),
+            ETX => {
+                if let Some(s) = start.take() {
+                    lengths.push(i - s);
+                }
+            }
+            _ => (),
+        }
+    }
+    if lengths.is_empty() {
+        return None;
+    }
+    match lengths.iter().sum::<usize>() / lengths.len() {
+        0..=600 => Some(TeleinfoMode::Legacy),
+        _ => Some(TeleinfoMode::Standard),
+    }
+}
+
+/// Return whether the 8th bit of the bytes of `sample` is an even parity bit
+fn has_parity_bit(sample: &[u8]) -> bool {
+    let high = sample.iter().filter(|b| *b & 0x80 != 0).count();
+    let even = sample.iter().filter(|b| b.count_ones() % 2 == 0).count();
+    high * 10 > sample.len() && even * 20 >= sample.len() * 19
+}
+
+fn wrong_speed(sample: &[u8]) -> Detection {
+    if sample.len() < 64 {
+        return Detection {
+            mode: None,
+            baud_rate: None,
+            confidence: 0.0,
+            recommendation: Recommendation::ReadMore,
+        };
+    }
+    // random bytes have 3.5 transitions between their 8 bits on average
+    let transitions = sample
+        .iter()
+        .map(|b| (b ^ (b >> 1)) & 0x7F)
+        .map(|t| t.count_ones() as f64)
+        .sum::<f64>()
+        / sample.len() as f64;
+    let (mode, confidence) = if transitions < 2.0 {
+        (TeleinfoMode::Legacy, (2.0 - transitions) / 1.5)
+    } else {
+        (TeleinfoMode::Standard, (transitions - 2.0) / 1.5)
+    };
+    if confidence < 0.3 {
+        return Detection {
+            mode: None,
+            baud_rate: None,
+            confidence: 0.0,
+            recommendation: Recommendation::ReadMore,
+        };
+    }
+    Detection {
+        mode: Some(mode),
+        baud_rate: Some(baud_rate(mode)),
+        confidence: confidence.min(1.0) * 0.8,
+        recommendation: Recommendation::Reopen(baud_rate(mode)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::detect::*;
+    use crate::simulator::fixture::frames;
+    /// Return `data` sent as 7E1 at `line_baud` as received by a 8N1 UART at `read_baud`
+    fn resample(data: &[u8], line_baud: u32, read_baud: u32) -> Vec<u8> {
+        let mut bits = vec![true; 20];
+        for &byte in data {
+            bits.push(false);
+            bits.extend((0..7).map(|i| byte >> i & 1 == 1));
+            bits.push(byte.count_ones() % 2 == 1);
+            bits.push(true);
+        }
+        bits.extend([true; 20].iter());
+        let level = |t: f64| *bits.get((t * line_baud as f64) as usize).unwrap_or(&true);
+        let bit_time = 1.0 / read_baud as f64;
+        let end = bits.len() as f64 / line_baud as f64;
+        let mut received = Vec::new();
+        let mut t = 0.0;
+        while t < end {
+            // wait for the falling edge of a start bit
+            let first = ((t * line_baud as f64).ceil() as usize).max(1);
+            let start = match (first..bits.len()).find(|&i| bits[i - 1] && !bits[i]) {
+                Some(i) => i as f64 / line_baud as f64,
+                None => break,
+            };
+            let byte = (0..8)
+                .filter(|k| level(start + bit_time * (1.5 + *k as f64)))
+                .fold(0u8, |acc, k| acc | 1 << k);
+            received.push(byte);
+            t = start + bit_time * 9.5;
+        }
+        received
+    }
+    #[test]
+    fn test_readable() {
+        let standard = include_bytes!("../assets/stream_standard_raw.txt");
+        let detection = detect(standard);
+        assert_eq!(detection.mode, Some(TeleinfoMode::Standard));
+        assert_eq!(detection.baud_rate, Some(9600));
+        assert_eq!(detection.recommendation, Recommendation::Keep);
+        assert!(detection.confidence > 0.9);
+        // 7E1 read as 8N1
+        for mode in [TeleinfoMode::Legacy, TeleinfoMode::Standard].iter() {
+            let speed = baud_rate(*mode);
+            let detection = detect(&resample(&frames(*mode, 3), speed, speed));
+            assert_eq!(detection.mode, Some(*mode));
+            assert_eq!(detection.recommendation, Recommendation::StripParity);
+        }
+    }
+    #[test]
+    fn test_wrong_speed() {
+        let legacy = resample(&frames(TeleinfoMode::Legacy, 10), 1200, 9600);
+        let detection = detect(&legacy);
+        assert_eq!(detection.mode, Some(TeleinfoMode::Legacy));
+        assert_eq!(detection.recommendation, Recommendation::Reopen(1200));
+        assert!(detection.confidence > 0.5);
+        let standard = resample(&frames(TeleinfoMode::Standard, 20), 9600, 1200);
+        let detection = detect(&standard);
+        assert_eq!(detection.mode, Some(TeleinfoMode::Standard));
+        assert_eq!(detection.recommendation, Recommendation::Reopen(9600));
+        assert!(detection.confidence > 0.3);
+        let detection = detect(&legacy[..20]);
+        assert_eq!(detection.recommendation, Recommendation::ReadMore);
+        assert_eq!(detection.mode, None);
+    }
+}
@@ -0,0 +1,94 @@
+//! Serialisation of messages back to the teleinfo wire format
+
+use crate::{parser, TeleinfoMessage};
+
+const STX: char = '\u{02}';
+const ETX: char = '\u{03}';
+
+/// Return the content of `message` between its STX and ETX characters, the inverse of
+/// `parse_frame`. Datasets are written in the order they were received and their checksums
+/// are calculated with the checksum method of the message, so the frame of a valid message
+/// is reproduced byte for byte
+/// # Example
+/// ```
+/// let frame = "\nADCO 031961098836 M\r\nPAPP 00120 $\r";
+/// let message = teleinfo_nom::parse_frame(frame).unwrap();
+/// assert_eq!(teleinfo_nom::format_frame(&message), frame);
+/// ```
+pub fn format_frame(message: &TeleinfoMessage) -> String {
+    message
+        .labels
+        .iter()
+        .map(|label| {
+            let value = &message.values[label];
+            parser::format_dataset(
+                message.checksum_method,
+                message.mode,
+                &(label, &value.value, value.checksum, value.horodate.clone()),
+            )
+        })
+        .collect()
+}
+
+/// Return `message` as sent on the bus, its frame enclosed in STX and ETX characters
+pub fn encode_message(message: &TeleinfoMessage) -> Vec<u8> {
+    format!("{}{}{}", STX, format_frame(message), ETX).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoder::{encode_message, format_frame};
+    use crate::{parse_frame, parser, TeleinfoDecoder};
+    #[test]
+    fn test_roundtrip() {
+        // message files end with the line feed of a text file after the frame
+        for data in [
+            &include_bytes!("../assets/message.txt")[..],
+            &include_bytes!("../assets/message_standard.txt")[..],
+        ]
+        .iter()
+        {
+            let message = String::from_utf8_lossy(data);
+            let frame = message.strip_suffix('\n').unwrap();
+            assert_eq!(format_frame(&parse_frame(frame).unwrap()), frame);
+        }
+        let streams = [
+            String::from_utf8_lossy(include_bytes!("../assets/stream_legacy_complete.txt")),
+            String::from_utf8_lossy(include_bytes!("../assets/stream_legacy_raw.txt")),
+            String::from_utf8_lossy(include_bytes!("../assets/stream_standard_complete.txt")),
+            String::from_utf8_lossy(include_bytes!("../assets/stream_standard_raw.txt")),
+        ];
+        let mut count = 0;
+        for stream in streams.iter() {
+            let mut input: &str = stream;
+            while let Ok((rest, frame)) = parser::get_message(input) {
+                let message = parse_frame(frame).unwrap();
+                if message.is_valid() {
+                    let end = input.len() - rest.len();
+                    let wire = &input[end - frame.len() - 2..end];
+                    assert_eq!(encode_message(&message), wire.as_bytes());
+                    count += 1;
+                }
+                input = rest;
+            }
+        }
+        assert_eq!(count, 5);
+    }
+    #[test]
+    fn test_checksums() {
+        // invalid checksums are replaced by the expected ones
+        let message = parse_frame("\nEASF01\t004855593\tX\r\nDATE\tH200214230804\t\tX\r").unwrap();
+        assert!(!message.is_valid());
+        let frame = format_frame(&message);
+        assert_eq!(
+            frame,
+            "\nEASF01\t004855593\tI\r\nDATE\tH200214230804\t\t;\r"
+        );
+        assert!(parse_frame(&frame).unwrap().is_valid());
+        let mut decoder = TeleinfoDecoder::new();
+        let decoded = decoder.feed(&encode_message(&message)).pop().unwrap();
+        let decoded = decoded.unwrap();
+        assert!(decoded.is_valid());
+        assert_eq!(format_frame(&decoded), frame);
+    }
+}
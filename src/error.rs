@@ -0,0 +1,156 @@
+//! Errors returned by the teleinfo parser
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::{FieldError, TeleinfoMode};
+
+/// TeleinfoError describes why a teleinfo stream or message could not be handled.
+/// Offsets are byte offsets in the message for the errors of `parse_frame` and the other
+/// parsing functions, `Framing` included. The `Framing` and `Interrupted` errors of a
+/// `TeleinfoDecoder` and the `Io` errors of the readers are at byte offsets in the stream, an
+/// `Io` error converted from an `io::Error` is at offset 0.
+/// Lines are the offending dataset without its framing characters
+#[derive(Debug)]
+pub enum TeleinfoError {
+    /// reading the source failed
+    Io { offset: usize, source: io::Error },
+    /// the data does not follow the message or dataset structure, the offset is in the message
+    /// when parsing a frame and in the stream when decoding one
+    Framing { offset: usize, line: String },
+    /// the meter aborted the message with an EOT character, `partial` is the data received
+    /// after its STX
+    Interrupted { offset: usize, partial: String },
+    /// the label is not defined in the specification for the message mode
+    UnknownLabel { offset: usize, line: String },
+    /// the checksum `received` with the dataset is not the one `expected` from its content
+    Checksum {
+        offset: usize,
+        line: String,
+        expected: char,
+        received: char,
+    },
+    /// the horodate of the dataset is not a valid date
+    InvalidHorodate { offset: usize, line: String },
+    /// a field needed to answer the request is missing from the message
+    MissingField { label: String },
+    /// the value of a field cannot be decoded as the type expected for its label
+    Field { source: FieldError },
+    /// the tariff option or period in `value` of the dataset `label` is not known to the crate
+    UnknownTariff { label: String, value: String },
+    /// the dataset is valid for another mode than the one of the message
+    ModeMismatch {
+        offset: usize,
+        line: String,
+        expected: TeleinfoMode,
+    },
+}
+
+impl TeleinfoError {
+    /// Return the byte offset of the error, None if the error is not tied to a position
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            TeleinfoError::Io { offset, .. }
+            | TeleinfoError::Framing { offset, .. }
+            | TeleinfoError::Interrupted { offset, .. }
+            | TeleinfoError::UnknownLabel { offset, .. }
+            | TeleinfoError::Checksum { offset, .. }
+            | TeleinfoError::InvalidHorodate { offset, .. }
+            | TeleinfoError::ModeMismatch { offset, .. } => Some(*offset),
+            TeleinfoError::MissingField { .. }
+            | TeleinfoError::Field { .. }
+            | TeleinfoError::UnknownTariff { .. } => None,
+        }
+    }
+
+    /// Return the offending line, None if the error is not tied to a line
+    pub fn line(&self) -> Option<&str> {
+        match self {
+            TeleinfoError::Framing { line, .. }
+            | TeleinfoError::UnknownLabel { line, .. }
+            | TeleinfoError::Checksum { line, .. }
+            | TeleinfoError::InvalidHorodate { line, .. }
+            | TeleinfoError::ModeMismatch { line, .. } => Some(line),
+            TeleinfoError::Io { .. }
+            | TeleinfoError::Interrupted { .. }
+            | TeleinfoError::MissingField { .. }
+            | TeleinfoError::Field { .. }
+            | TeleinfoError::UnknownTariff { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for TeleinfoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TeleinfoError::Io { offset, source } => {
+                write!(f, "I/O error after {} bytes: {}", offset, source)
+            }
+            TeleinfoError::Framing { offset, line } => {
+                write!(f, "framing error at byte {}: {:?}", offset, line)
+            }
+            TeleinfoError::Interrupted { offset, partial } => write!(
+                f,
+                "message at byte {} interrupted after {} bytes",
+                offset,
+                partial.len()
+            ),
+            TeleinfoError::UnknownLabel { offset, line } => {
+                write!(f, "unknown label at byte {}: {:?}", offset, line)
+            }
+            TeleinfoError::Checksum {
+                offset,
+                line,
+                expected,
+                received,
+            } => write!(
+                f,
+                "bad checksum at byte {}, {:?} instead of {:?}: {:?}",
+                offset, received, expected, line
+            ),
+            TeleinfoError::InvalidHorodate { offset, line } => {
+                write!(f, "invalid horodate at byte {}: {:?}", offset, line)
+            }
+            TeleinfoError::MissingField { label } => write!(f, "missing field {}", label),
+            TeleinfoError::Field { source } => write!(f, "{}", source),
+            TeleinfoError::UnknownTariff { label, value } => {
+                write!(f, "unknown tariff in {}: {:?}", label, value)
+            }
+            TeleinfoError::ModeMismatch {
+                offset,
+                line,
+                expected,
+            } => write!(
+                f,
+                "dataset at byte {} is not in {:?} mode: {:?}",
+                offset, expected, line
+            ),
+        }
+    }
+}
+
+/// Errors of a source which does not track its position are reported at offset 0
+impl From<io::Error> for TeleinfoError {
+    fn from(source: io::Error) -> Self {
+        TeleinfoError::Io { offset: 0, source }
+    }
+}
+
+/// Decoding errors of a field, to use `?` on the typed accessors of `TeleinfoMessage` in
+/// functions returning a `TeleinfoError`
+impl From<FieldError> for TeleinfoError {
+    fn from(source: FieldError) -> Self {
+        TeleinfoError::Field { source }
+    }
+}
+
+impl Error for TeleinfoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TeleinfoError::Io { source, .. } => Some(source),
+            TeleinfoError::Field { source } => Some(source),
+            _ => None,
+        }
+    }
+}
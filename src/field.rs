@@ -0,0 +1,180 @@
+//! Typed decoding of the values sent for each label
+
+use std::fmt;
+use std::str::FromStr;
+
+/// TeleinfoField represents the decoded value of a message line
+#[derive(Clone, Debug, PartialEq)]
+pub enum TeleinfoField {
+    /// active energy index in Wh
+    Energy(u64),
+    /// reactive energy index in VArh
+    ReactiveEnergy(u64),
+    /// apparent power in VA
+    ApparentPower(u32),
+    /// active power in W
+    ActivePower(u32),
+    /// current in A
+    Current(u16),
+    /// voltage in V
+    Voltage(u16),
+    /// number without unit, like a tariff index or a duration in minutes
+    Integer(u32),
+    /// trimmed text
+    Text(String),
+}
+
+/// FieldError is returned when a value cannot be decoded as the type expected for its label
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldError {
+    pub label: String,
+    pub value: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid value {:?} for label {}", self.value, self.label)
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum FieldKind {
+    Energy,
+    ReactiveEnergy,
+    ApparentPower,
+    ApparentPowerKva,
+    ActivePower,
+    Current,
+    Voltage,
+    Integer,
+    Text,
+}
+
+fn field_kind(label: &str) -> FieldKind {
+    match label {
+        "BASE" | "HCHC" | "HCHP" | "EJPHN" | "EJPHPM" | "BBRHCJB" | "BBRHPJB" | "BBRHCJW"
+        | "BBRHPJW" | "BBRHCJR" | "BBRHPJR" | "EAST" | "EAIT" => FieldKind::Energy,
+        l if l.starts_with("EASF") || l.starts_with("EASD") => FieldKind::Energy,
+        "ERQ1" | "ERQ2" | "ERQ3" | "ERQ4" => FieldKind::ReactiveEnergy,
+        "PAPP" | "SINSTS" | "SINSTS1" | "SINSTS2" | "SINSTS3" | "SINSTI" => {
+            FieldKind::ApparentPower
+        }
+        l if l.starts_with("SMAXSN") || l.starts_with("SMAXIN") => FieldKind::ApparentPower,
+        "PREF" | "PCOUP" => FieldKind::ApparentPowerKva,
+        "PMAX" | "CCASN" | "CCASN-1" | "CCAIN" | "CCAIN-1" => FieldKind::ActivePower,
+        "ISOUSC" | "IINST" | "IINST1" | "IINST2" | "IINST3" | "ADPS" | "IMAX" | "IMAX1"
+        | "IMAX2" | "IMAX3" | "ADIR1" | "ADIR2" | "ADIR3" | "IRMS1" | "IRMS2" | "IRMS3" => {
+            FieldKind::Current
+        }
+        "URMS1" | "URMS2" | "URMS3" | "UMOY1" | "UMOY2" | "UMOY3" => FieldKind::Voltage,
+        "PEJP" | "VTIC" | "NTARF" | "NJOURF" | "NJOURF+1" | "RELAIS" | "DPM1" | "FPM1" | "DPM2"
+        | "FPM2" | "DPM3" | "FPM3" => FieldKind::Integer,
+        _ => FieldKind::Text,
+    }
+}
+
+fn parse_number<T: FromStr>(value: &str) -> Option<T> {
+    value.trim().parse().ok()
+}
+
+impl TeleinfoField {
+    /// Decode `value` according to the type of `label`
+    /// # Example
+    /// ```
+    /// use teleinfo_nom::TeleinfoField;
+    /// assert_eq!(TeleinfoField::decode("EASF03", "000487131"), Ok(TeleinfoField::Energy(487131)));
+    /// assert_eq!(TeleinfoField::decode("PREF", "30"), Ok(TeleinfoField::ApparentPower(30000)));
+    /// assert_eq!(TeleinfoField::decode("LTARF", "   HC  BLANC    "),
+    ///            Ok(TeleinfoField::Text("HC  BLANC".to_string())));
+    /// assert!(TeleinfoField::decode("IRMS1", "A03").is_err());
+    /// ```
+    pub fn decode(label: &str, value: &str) -> Result<TeleinfoField, FieldError> {
+        let field = match field_kind(label) {
+            FieldKind::Energy => parse_number(value).map(TeleinfoField::Energy),
+            FieldKind::ReactiveEnergy => parse_number(value).map(TeleinfoField::ReactiveEnergy),
+            FieldKind::ApparentPower => parse_number(value).map(TeleinfoField::ApparentPower),
+            FieldKind::ApparentPowerKva => parse_number::<u32>(value)
+                .and_then(|kva| kva.checked_mul(1000))
+                .map(TeleinfoField::ApparentPower),
+            FieldKind::ActivePower => parse_number(value).map(TeleinfoField::ActivePower),
+            FieldKind::Current => parse_number(value).map(TeleinfoField::Current),
+            FieldKind::Voltage => parse_number(value).map(TeleinfoField::Voltage),
+            FieldKind::Integer => parse_number(value).map(TeleinfoField::Integer),
+            FieldKind::Text => Some(TeleinfoField::Text(value.trim().to_string())),
+        };
+        field.ok_or_else(|| FieldError {
+            label: label.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    /// Return the numeric value of the field, None for text
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            TeleinfoField::Energy(x) | TeleinfoField::ReactiveEnergy(x) => Some(*x),
+            TeleinfoField::ApparentPower(x)
+            | TeleinfoField::ActivePower(x)
+            | TeleinfoField::Integer(x) => Some(u64::from(*x)),
+            TeleinfoField::Current(x) | TeleinfoField::Voltage(x) => Some(u64::from(*x)),
+            TeleinfoField::Text(_) => None,
+        }
+    }
+
+    /// Return the text of the field, None for numeric values
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            TeleinfoField::Text(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::field::*;
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            TeleinfoField::decode("BBRHCJB", "001478389"),
+            Ok(TeleinfoField::Energy(1478389))
+        );
+        assert_eq!(
+            TeleinfoField::decode("ERQ2", "000000012"),
+            Ok(TeleinfoField::ReactiveEnergy(12))
+        );
+        assert_eq!(
+            TeleinfoField::decode("SMAXSN1-1", "03129"),
+            Ok(TeleinfoField::ApparentPower(3129))
+        );
+        assert_eq!(
+            TeleinfoField::decode("CCASN", "01650"),
+            Ok(TeleinfoField::ActivePower(1650))
+        );
+        assert_eq!(
+            TeleinfoField::decode("IINST", "001"),
+            Ok(TeleinfoField::Current(1))
+        );
+        assert_eq!(
+            TeleinfoField::decode("UMOY3", "236"),
+            Ok(TeleinfoField::Voltage(236))
+        );
+        assert_eq!(
+            TeleinfoField::decode("NTARF", "03"),
+            Ok(TeleinfoField::Integer(3))
+        );
+        assert_eq!(
+            TeleinfoField::decode("OPTARIF", "BBR("),
+            Ok(TeleinfoField::Text("BBR(".to_string()))
+        );
+        assert_eq!(
+            TeleinfoField::decode("PAPP", "0012O"),
+            Err(FieldError {
+                label: "PAPP".to_string(),
+                value: "0012O".to_string()
+            })
+        );
+        assert!(TeleinfoField::decode("PREF", "9999999").is_err());
+    }
+}
@@ -0,0 +1,274 @@
+//! Configurable parsing of the content of a message between its STX and ETX characters
+
+use crate::parser::{self, ChecksumMethod, LabelKind};
+use crate::{TeleinfoError, TeleinfoMessage, TeleinfoMode, TeleinfoTuple};
+
+/// Validation describes how malformed datasets and datasets with an invalid checksum are handled
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Validation {
+    /// parsing stops at the first malformed dataset, datasets with an invalid checksum are kept
+    /// and the message is marked as invalid
+    Partial,
+    /// the first malformed dataset, unknown label or invalid checksum is an error
+    Strict,
+    /// malformed datasets and datasets with an invalid checksum are skipped
+    Lenient,
+}
+
+/// TeleinfoParser parses message frames with a given validation and checksum method.
+/// When no checksum method is configured, it is detected from the first frames whose
+/// datasets validate with one method only, and kept for the following frames
+#[derive(Clone, Debug, PartialEq)]
+pub struct TeleinfoParser {
+    checksum_method: Option<ChecksumMethod>,
+    detected_method: Option<ChecksumMethod>,
+    validation: Validation,
+}
+
+struct Dataset<'a> {
+    offset: usize,
+    line: &'a str,
+    tuple: TeleinfoTuple<'a>,
+}
+
+impl Default for TeleinfoParser {
+    fn default() -> Self {
+        TeleinfoParser::new()
+    }
+}
+
+impl TeleinfoParser {
+    /// Return a parser with `Validation::Partial` and checksum method detection
+    pub fn new() -> TeleinfoParser {
+        TeleinfoParser {
+            checksum_method: None,
+            detected_method: None,
+            validation: Validation::Partial,
+        }
+    }
+
+    /// Use `method` for checksums instead of detecting it
+    pub fn checksum_method(mut self, method: ChecksumMethod) -> TeleinfoParser {
+        self.checksum_method = Some(method);
+        self
+    }
+
+    /// Use `validation` to handle malformed and invalid datasets
+    pub fn validation(mut self, validation: Validation) -> TeleinfoParser {
+        self.validation = validation;
+        self
+    }
+
+    /// Return the configured or detected checksum method, None while it is unknown
+    pub fn get_checksum_method(&self) -> Option<ChecksumMethod> {
+        self.checksum_method.or(self.detected_method)
+    }
+
+    /// Parse `frame`, the content of a message between its STX and ETX characters
+    /// # Example
+    /// ```
+    /// use teleinfo_nom::{parser::ChecksumMethod, TeleinfoParser};
+    /// let mut parser = TeleinfoParser::new();
+    /// // standard meter using the legacy checksum method
+    /// let message = parser.parse("\nEASF01\t004855593\t@\r\nEASF02\t014090959\t?\r").unwrap();
+    /// assert!(message.is_valid());
+    /// assert_eq!(message.get_checksum_method(), ChecksumMethod::WithoutSeparator);
+    /// assert_eq!(parser.get_checksum_method(), Some(ChecksumMethod::WithoutSeparator));
+    /// ```
+    pub fn parse(&mut self, frame: &str) -> Result<TeleinfoMessage, TeleinfoError> {
+        let (mode, mut datasets, mut complete) = match self.validation {
+            Validation::Lenient => lenient_datasets(frame)?,
+            _ => self.ordered_datasets(frame)?,
+        };
+        let method = self.method_for(mode, &datasets);
+        match self.validation {
+            Validation::Partial => (),
+            Validation::Strict => {
+                if let Some(d) = datasets
+                    .iter()
+                    .find(|d| !parser::validate_with(method, mode, &d.tuple))
+                {
+                    return Err(checksum_error(method, mode, d));
+                }
+            }
+            Validation::Lenient => {
+                let count = datasets.len();
+                let first_invalid = datasets
+                    .iter()
+                    .find(|d| !parser::validate_with(method, mode, &d.tuple))
+                    .map(|d| checksum_error(method, mode, d));
+                datasets.retain(|d| parser::validate_with(method, mode, &d.tuple));
+                if datasets.is_empty() {
+                    // a frame without any valid dataset reports the first bad checksum
+                    return Err(
+                        first_invalid.unwrap_or_else(|| parser::line_error(frame, frame, None))
+                    );
+                }
+                complete &= datasets.len() == count;
+            }
+        }
+        let lines: Vec<TeleinfoTuple> = datasets.into_iter().map(|d| d.tuple).collect();
+        let valid = complete && lines.iter().all(|l| parser::validate_with(method, mode, l));
+        Ok(TeleinfoMessage::from_datasets(mode, method, valid, lines))
+    }
+
+    fn ordered_datasets<'a>(
+        &self,
+        frame: &'a str,
+    ) -> Result<(TeleinfoMode, Vec<Dataset<'a>>, bool), TeleinfoError> {
+        let (_, (_, mode)) =
+            parser::parser_message(frame).map_err(|_| parser::line_error(frame, frame, None))?;
+        let mut input = frame;
+        let mut datasets = Vec::new();
+        while !input.is_empty() {
+            let offset = frame.len() - input.len();
+            let (rest, tuple) = match parser::parser_dataset(mode, input) {
+                Ok(r) => r,
+                Err(_) if self.validation == Validation::Strict => {
+                    return Err(parser::line_error(frame, input, Some(mode)))
+                }
+                Err(_) => break,
+            };
+            let line = input[..input.len() - rest.len()].trim_matches(['\n', '\r']);
+            if self.validation == Validation::Strict
+                && parser::label_kind(mode, tuple.0) == LabelKind::Unknown
+            {
+                return Err(TeleinfoError::UnknownLabel {
+                    offset,
+                    line: line.to_string(),
+                });
+            }
+            datasets.push(Dataset {
+                offset,
+                line,
+                tuple,
+            });
+            input = rest;
+        }
+        Ok((mode, datasets, input.is_empty()))
+    }
+
+    fn method_for(&mut self, mode: TeleinfoMode, datasets: &[Dataset]) -> ChecksumMethod {
+        if let Some(method) = self.get_checksum_method() {
+            return method;
+        }
+        let default = ChecksumMethod::default_for(mode);
+        let count = |method| {
+            datasets
+                .iter()
+                .filter(|d| parser::validate_with(method, mode, &d.tuple))
+                .count()
+        };
+        let (with_default, with_other) = (count(default), count(default.other()));
+        if with_default > with_other {
+            self.detected_method = Some(default);
+        } else if with_other > with_default {
+            self.detected_method = Some(default.other());
+        }
+        self.detected_method.unwrap_or(default)
+    }
+}
+
+/// Return the checksum error of the dataset `d`
+fn checksum_error(method: ChecksumMethod, mode: TeleinfoMode, d: &Dataset) -> TeleinfoError {
+    TeleinfoError::Checksum {
+        offset: d.offset,
+        line: d.line.to_string(),
+        expected: parser::expected_checksum(method, mode, &d.tuple),
+        received: d.tuple.2,
+    }
+}
+
+fn lenient_datasets(frame: &str) -> Result<(TeleinfoMode, Vec<Dataset<'_>>, bool), TeleinfoError> {
+    let chunks = parser::split_datasets(frame);
+    let mode = chunks
+        .iter()
+        .find_map(|(_, d)| parser::parser_message(d).ok().map(|(_, (_, mode))| mode))
+        .ok_or_else(|| parser::line_error(frame, frame, None))?;
+    let datasets: Vec<Dataset> = chunks
+        .iter()
+        .filter_map(
+            |&(offset, chunk)| match parser::parser_dataset(mode, chunk) {
+                Ok(("", tuple)) => Some(Dataset {
+                    offset,
+                    line: chunk.trim_matches(['\n', '\r']),
+                    tuple,
+                }),
+                _ => None,
+            },
+        )
+        .collect();
+    let complete = datasets.len() == chunks.len();
+    Ok((mode, datasets, complete))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frame::{TeleinfoParser, Validation};
+    use crate::parser::ChecksumMethod;
+    #[test]
+    fn test_checksum_method_detection() {
+        let legacy_method = "\nEASF01\t004855593\t@\r\nEASF02\t014090959\t?\r";
+        let mut parser = TeleinfoParser::new();
+        assert_eq!(parser.get_checksum_method(), None);
+        // undecidable frame keeps the default method without locking it
+        let message = parser.parse("\nEASF01\t004855593\tX\r").unwrap();
+        assert_eq!(message.checksum_method, ChecksumMethod::WithSeparator);
+        assert_eq!(parser.get_checksum_method(), None);
+        assert!(parser.parse(legacy_method).unwrap().valid);
+        // detected method is kept even for a frame validating with the default method
+        let message = parser.parse("\nEASF01\t004855593\tI\r").unwrap();
+        assert!(!message.valid);
+        assert_eq!(message.get_invalid_labels(), vec!["EASF01".to_string()]);
+        let mut configured = TeleinfoParser::new().checksum_method(ChecksumMethod::WithSeparator);
+        assert!(!configured.parse(legacy_method).unwrap().valid);
+        assert_eq!(
+            configured.get_checksum_method(),
+            Some(ChecksumMethod::WithSeparator)
+        );
+    }
+    #[test]
+    fn test_validation() {
+        let frame = "\nEASF01\t004855593\t@\r\nEASF02\t014090959\t?\r\nEASF03\t000487131\tX\r";
+        let mut strict = TeleinfoParser::new()
+            .validation(Validation::Strict)
+            .checksum_method(ChecksumMethod::WithoutSeparator);
+        match strict.parse(frame) {
+            Err(crate::TeleinfoError::Checksum {
+                offset,
+                line,
+                expected,
+                received,
+            }) => {
+                assert_eq!(offset, 40);
+                assert_eq!(line, "EASF03\t000487131\tX");
+                assert_eq!((expected, received), ('3', 'X'));
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        let message = TeleinfoParser::new()
+            .validation(Validation::Lenient)
+            .parse(frame)
+            .unwrap();
+        assert_eq!(message.values.len(), 2);
+        assert_eq!(message.checksum_method, ChecksumMethod::WithoutSeparator);
+        // without any valid dataset the first bad checksum is reported
+        let invalid = "\nADCO 031961098836 X\r\nPAPP 00120 #\r";
+        match TeleinfoParser::new()
+            .validation(Validation::Lenient)
+            .parse(invalid)
+        {
+            Err(crate::TeleinfoError::Checksum {
+                offset,
+                line,
+                expected,
+                received,
+            }) => {
+                assert_eq!(offset, 0);
+                assert_eq!(line, "ADCO 031961098836 X");
+                assert_eq!((expected, received), ('M', 'X'));
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Dates sent in standard mode messages (horodates) in French legal time
+
+use chrono::{
+    DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+
+/// Season tells the offset from UTC of a date in French legal time
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Season {
+    /// winter time, UTC+1
+    Winter,
+    /// summer time, UTC+2
+    Summer,
+}
+
+impl Season {
+    /// Return the offset from UTC of the season
+    pub fn offset(self) -> FixedOffset {
+        let hours = match self {
+            Season::Winter => 1,
+            Season::Summer => 2,
+        };
+        FixedOffset::east_opt(hours * 3600).unwrap()
+    }
+
+    /// Return the season of `time` in French legal time, summer time running from the last
+    /// Sunday of March at 2:00 to the last Sunday of October at 3:00. The hour lived twice in
+    /// October is taken as summer time
+    pub fn of(time: NaiveDateTime) -> Season {
+        let last_sunday = |month: u32, hour: u32| {
+            let mut day = NaiveDate::from_ymd_opt(time.year(), month, 31).unwrap_or(time.date());
+            while day.weekday() != Weekday::Sun {
+                day = day.pred_opt().unwrap_or(day);
+            }
+            day.and_time(NaiveTime::from_hms_opt(hour, 0, 0).unwrap_or(NaiveTime::MIN))
+        };
+        if time >= last_sunday(3, 2) && time < last_sunday(10, 3) {
+            Season::Summer
+        } else {
+            Season::Winter
+        }
+    }
+}
+
+/// TeleinfoDate represents a date sent in a teleinfo message in standard mode
+#[derive(Clone, Debug, PartialEq)]
+pub struct TeleinfoDate {
+    /// char representing the season: 'H' in winter, 'E' in summer, lower case when the meter
+    /// clock is degraded, ' ' when the date has no season
+    pub season: char,
+    /// the date in French legal time, with the offset of its season or, without season, the
+    /// offset of French legal time at that date
+    pub date: DateTime<FixedOffset>,
+    /// the horodate as received
+    pub raw_value: String,
+}
+
+impl TeleinfoDate {
+    /// Return the date of `raw`, a season character followed by the date as YYMMDDhhmmss,
+    /// None if it is malformed
+    /// # Example
+    /// ```
+    /// let date = teleinfo_nom::TeleinfoDate::parse("E200714120000").unwrap();
+    /// assert_eq!(date.get_season(), Some(teleinfo_nom::Season::Summer));
+    /// assert_eq!(date.get_utc().to_rfc3339(), "2020-07-14T10:00:00+00:00");
+    /// assert_eq!(date.get_naive_local().to_string(), "2020-07-14 12:00:00");
+    /// ```
+    pub fn parse(raw: &str) -> Option<TeleinfoDate> {
+        let season = raw.chars().next()?;
+        let digits = raw.get(1..)?;
+        if digits.len() != 12 || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let naive = NaiveDateTime::parse_from_str(digits, "%y%m%d%H%M%S").ok()?;
+        let offset = match season {
+            'H' | 'h' => Season::Winter.offset(),
+            'E' | 'e' => Season::Summer.offset(),
+            ' ' => Season::of(naive).offset(),
+            _ => return None,
+        };
+        Some(TeleinfoDate {
+            season,
+            date: offset.from_local_datetime(&naive).single()?,
+            raw_value: raw.to_string(),
+        })
+    }
+
+    /// Return the season sent with the date, None if the date has no season
+    pub fn get_season(&self) -> Option<Season> {
+        match self.season.to_ascii_uppercase() {
+            'H' => Some(Season::Winter),
+            'E' => Some(Season::Summer),
+            _ => None,
+        }
+    }
+
+    /// Return whether the meter clock was degraded when it sent the date
+    pub fn is_clock_degraded(&self) -> bool {
+        self.season.is_ascii_lowercase()
+    }
+
+    /// Return the date in UTC
+    pub fn get_utc(&self) -> DateTime<Utc> {
+        self.date.with_timezone(&Utc)
+    }
+
+    /// Return the date in French legal time, without offset
+    pub fn get_naive_local(&self) -> NaiveDateTime {
+        self.date.naive_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::horodate::*;
+    #[test]
+    fn test_seasons() {
+        let winter = TeleinfoDate::parse("H201025020000").unwrap();
+        let summer = TeleinfoDate::parse("E201025020000").unwrap();
+        // the October hour lived twice is told apart by the season
+        assert_eq!((winter.get_utc() - summer.get_utc()).num_hours(), 1);
+        assert_eq!(winter.get_naive_local(), summer.get_naive_local());
+        let degraded = TeleinfoDate::parse("h200214230804").unwrap();
+        assert!(degraded.is_clock_degraded());
+        assert_eq!(degraded.get_season(), Some(Season::Winter));
+        assert_eq!(degraded.get_utc().to_rfc3339(), "2020-02-14T22:08:04+00:00");
+        assert!(!winter.is_clock_degraded());
+        // dates without season follow French legal time
+        let blank = TeleinfoDate::parse(" 200714060000").unwrap();
+        assert_eq!(blank.get_season(), None);
+        assert_eq!(blank.date.offset(), &Season::Summer.offset());
+        let blank = TeleinfoDate::parse(" 200214060000").unwrap();
+        assert_eq!(blank.date.offset(), &Season::Winter.offset());
+        assert_eq!(TeleinfoDate::parse("X200214060000"), None);
+        assert_eq!(TeleinfoDate::parse("H2002140600"), None);
+        assert_eq!(TeleinfoDate::parse("H201345060000"), None);
+    }
+}
@@ -4,9 +4,12 @@
 extern crate chrono;
 extern crate nom;
 
-use chrono::{offset::Local, DateTime};
 use std::collections::HashMap;
-use std::io::{self, Error, ErrorKind, Read, Result};
+use std::io::{self, Read};
+
+const STX: u8 = 0x02;
+const ETX: u8 = 0x03;
+const EOT: u8 = 0x04;
 
 type TeleinfoTuple<'a> = (&'a str, &'a str, char, Option<TeleinfoDate>);
 
@@ -17,22 +20,19 @@ pub enum TeleinfoMode {
     Legacy,
 }
 
-/// TeleinfoDate struct represents a date sent in a teleinfo message in standard mode
-#[derive(Clone, Debug, PartialEq)]
-pub struct TeleinfoDate {
-    /// char representing the season might by 'h', 'e', or ' ' upper or lower case depending
-    /// whether meter clock is synchronized or not
-    season: char,
-    /// the DateTime parsed from Teleinfo message
-    date: DateTime<Local>,
-    raw_value: String,
-}
-
 /// TeleinfoValue represents the value and date of a message line from Teleinfo
+/// * checksum the checksum received with the line
+/// * expected_checksum the checksum calculated from the line content
+/// * valid whether both checksums match
+/// * parity_error whether a byte of the line was received with a parity error
 #[derive(Clone, Debug, PartialEq)]
 pub struct TeleinfoValue {
     pub value: String,
     pub horodate: Option<TeleinfoDate>,
+    pub checksum: char,
+    pub expected_checksum: char,
+    pub valid: bool,
+    pub parity_error: bool,
 }
 
 /// TeleinfoMessageType describes if the message is a short message or a normal message
@@ -49,19 +49,53 @@ pub enum TeleinfoMeterType {
     TriPhase,
 }
 
+/// TeleinfoMeterRole describes if the meter measures consumption only or also the injection
+/// of a producer
+#[derive(Debug, PartialEq)]
+pub enum TeleinfoMeterRole {
+    Consumer,
+    Producer,
+}
+
 /// Representation of a full message from teleinfo
 /// * values is hashmap resolving index to TeleinfoValue
+/// * labels the labels of the values in the order they were received
 /// * mode the mode of the messae as TeleinfoMode
 /// * valid whether the message is valid checksum wise
+/// * checksum_method the checksum method used to validate the message
 #[derive(Clone, Debug, PartialEq)]
 pub struct TeleinfoMessage {
     values: HashMap<String, TeleinfoValue>,
+    labels: Vec<String>,
     mode: TeleinfoMode,
     valid: bool,
+    checksum_method: ChecksumMethod,
 }
 
 impl TeleinfoMessage {
-    /// Return message type as `TeleinfoMessageType`
+    fn from_datasets(
+        mode: TeleinfoMode,
+        method: ChecksumMethod,
+        valid: bool,
+        lines: Vec<TeleinfoTuple<'_>>,
+    ) -> TeleinfoMessage {
+        let mut labels: Vec<String> = Vec::new();
+        for line in lines.iter() {
+            if !labels.iter().any(|l| l == line.0) {
+                labels.push(line.0.to_string());
+            }
+        }
+        TeleinfoMessage {
+            values: parsed_vector_to_values(mode, method, lines),
+            labels,
+            mode,
+            valid,
+            checksum_method: method,
+        }
+    }
+
+    /// Return message type as `TeleinfoMessageType`: a legacy message is short when it carries
+    /// ADIR or IINST datasets of a three-phase meter and no other label than ADCO
     /// # Example
     /// ```
     /// use std::fs::File;
@@ -73,10 +107,16 @@ impl TeleinfoMessage {
         match self.mode {
             TeleinfoMode::Standard => TeleinfoMessageType::Normal,
             TeleinfoMode::Legacy => {
-                if self.values.contains_key("OPTARIF") {
-                    TeleinfoMessageType::Normal
-                } else {
+                let short = |label: &String| {
+                    ["ADIR1", "ADIR2", "ADIR3", "IINST1", "IINST2", "IINST3"]
+                        .contains(&label.as_str())
+                };
+                if self.labels.iter().any(short)
+                    && self.labels.iter().all(|l| l == "ADCO" || short(l))
+                {
                     TeleinfoMessageType::Short
+                } else {
+                    TeleinfoMessageType::Normal
                 }
             }
         }
@@ -110,6 +150,91 @@ impl TeleinfoMessage {
         }
     }
 
+    /// Return the overcurrent alert of a legacy three-phase short frame, None for other
+    /// messages
+    /// # Example
+    /// ```
+    /// let message = teleinfo_nom::parse_frame(
+    ///     "\nADIR2 063 +\r\nADCO 031961098836 M\r\nIINST1 012 K\r\nIINST2 063 R\r\nIINST3 008 R\r")
+    ///     .unwrap();
+    /// let alert = message.get_overcurrent_alert().unwrap();
+    /// assert_eq!(alert.get_phases(), vec![2]);
+    /// assert_eq!(alert.currents, [Some(12), Some(63), Some(8)]);
+    /// ```
+    pub fn get_overcurrent_alert(&self) -> Option<OvercurrentAlert> {
+        OvercurrentAlert::from_message(self)
+    }
+
+    /// Return the message with its values updated by the ones of `short`, to complete a short
+    /// frame with the context of the last full frame
+    pub fn merge(&self, short: &TeleinfoMessage) -> TeleinfoMessage {
+        let mut merged = self.clone();
+        for label in short.labels.iter() {
+            if !merged.labels.contains(label) {
+                merged.labels.push(label.clone());
+            }
+            merged
+                .values
+                .insert(label.clone(), short.values[label].clone());
+        }
+        merged.valid = self.valid && short.valid;
+        merged
+    }
+
+    /// Return meter role as `TeleinfoMeterRole`, producers sending their injected energy in
+    /// standard mode
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// assert_eq!(result.get_meter_role(),teleinfo_nom::TeleinfoMeterRole::Consumer)
+    /// ```
+    pub fn get_meter_role(&self) -> TeleinfoMeterRole {
+        let producer = self.values.contains_key("EAIT")
+            || self.get_status().is_some_and(|status| status.producer);
+        match (self.mode, producer) {
+            (TeleinfoMode::Standard, true) => TeleinfoMeterRole::Producer,
+            _ => TeleinfoMeterRole::Consumer,
+        }
+    }
+
+    /// Return the total injected energy (EAIT) in Wh as Option, None if the meter is not a
+    /// producer
+    pub fn get_injected_energy(&self) -> Option<Result<u64, FieldError>> {
+        self.get_energy("EAIT".to_string())
+    }
+
+    /// Return the injected apparent power (SINSTI) in VA as Option, None if the meter is not
+    /// a producer
+    pub fn get_injected_power(&self) -> Option<Result<u32, FieldError>> {
+        self.get_apparent_power("SINSTI".to_string())
+    }
+
+    /// Return the consumed minus the injected apparent power in VA as Option, negative when
+    /// the installation injects more than it consumes. None if the consumed power (SINSTS,
+    /// PAPP in legacy mode) or the injected power of a producer is missing or malformed
+    /// # Example
+    /// ```
+    /// let message = teleinfo_nom::parse_frame(
+    ///     "\nEAIT\t000001000\tF\r\nSINSTS\t00300\tI\r\nSINSTI\t01200\t?\r").unwrap();
+    /// assert_eq!(message.get_meter_role(), teleinfo_nom::TeleinfoMeterRole::Producer);
+    /// assert_eq!(message.get_injected_energy().unwrap().unwrap(), 1000);
+    /// assert_eq!(message.get_net_power(), Some(-900));
+    /// ```
+    pub fn get_net_power(&self) -> Option<i64> {
+        let consumed = match self.mode {
+            TeleinfoMode::Legacy => self.get_apparent_power("PAPP".to_string())?,
+            TeleinfoMode::Standard => self.get_apparent_power("SINSTS".to_string())?,
+        }
+        .ok()?;
+        let injected = match self.get_meter_role() {
+            TeleinfoMeterRole::Producer => self.get_injected_power()?.ok()?,
+            TeleinfoMeterRole::Consumer => 0,
+        };
+        Some(i64::from(consumed) - i64::from(injected))
+    }
+
     /// Return the index currently increasing
     ///
     /// # Example
@@ -117,61 +242,71 @@ impl TeleinfoMessage {
     /// use std::fs::File;
     /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
     /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
-    /// assert_eq!(result.get_current_index(),"EASF03".to_string());
+    /// assert_eq!(result.get_current_index().unwrap(),"EASF03".to_string());
     /// let mut stream = File::open("assets/stream_legacy_raw.txt").unwrap();
     /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
-    /// assert_eq!(result.get_current_index(),"BBRHPJB".to_string())
+    /// assert_eq!(result.get_current_index().unwrap(),"BBRHPJB".to_string())
     /// ```
-    pub fn get_current_index(&self) -> String {
+    pub fn get_current_index(&self) -> Result<String, TeleinfoError> {
         match self.mode {
             TeleinfoMode::Legacy => self.get_current_index_legacy(),
             TeleinfoMode::Standard => self.get_current_index_standard(),
         }
     }
 
-    fn get_current_index_legacy(&self) -> String {
-        match self.get_value("PTEC".to_string()).unwrap().value.as_str() {
-            "TH.." => "BASE".to_string(),
-            "HC.." => "HCHC".to_string(),
-            "HP.." => "HCHP".to_string(),
-            "HN.." => "EJPHN".to_string(),
-            "PM.." => "EJPPM".to_string(),
-            "HCJB" => "BBRHCJB".to_string(),
-            "HCJW" => "BBRHCJW".to_string(),
-            "HCJR" => "BBRHCJR".to_string(),
-            "HPJB" => "BBRHPJB".to_string(),
-            "HPJW" => "BBRHPJW".to_string(),
-            "HPJR" => "BBRHPJR".to_string(),
-            &_ => "BASE".to_string(),
-        }
+    fn get_current_index_legacy(&self) -> Result<String, TeleinfoError> {
+        let ptec = &self.get_mandatory_value("PTEC")?.value;
+        Ok(TariffPeriod::from_ptec(ptec)?.index.to_string())
+    }
+
+    fn get_current_index_standard(&self) -> Result<String, TeleinfoError> {
+        let idx = &self.get_mandatory_value("NTARF")?.value;
+        Ok(format!("EASF{}", idx))
     }
 
-    fn get_current_index_standard(&self) -> String {
-        let idx = &self.get_value("NTARF".to_string()).unwrap().value;
-        format!("EASF{}", idx)
+    /// Return the tariff option of the supplier contract, read from OPTARIF in legacy mode and
+    /// from NGTF in standard mode, an UnknownTariff error if the crate does not know it
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// use teleinfo_nom::TariffOption;
+    /// let mut stream = File::open("assets/stream_legacy_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// assert_eq!(result.get_tariff_option().unwrap(), TariffOption::Tempo { program: Some('(') });
+    /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// assert_eq!(result.get_tariff_option().unwrap(), TariffOption::Tempo { program: None });
+    /// ```
+    pub fn get_tariff_option(&self) -> Result<TariffOption, TeleinfoError> {
+        match self.mode {
+            TeleinfoMode::Legacy => {
+                TariffOption::from_optarif(&self.get_mandatory_value("OPTARIF")?.value)
+            }
+            TeleinfoMode::Standard => {
+                TariffOption::from_ngtf(&self.get_mandatory_value("NGTF")?.value)
+            }
+        }
     }
 
-    /// Return all relevant billing indices for the message
+    /// Return all relevant billing indices for the message. In standard mode, the supplier
+    /// indices used by the contract as returned by `get_supplier_indices`, or the EASF indices
+    /// of the message when it has no NGTF
     /// # Example
     /// ```
     /// use std::fs::File;
     /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
     /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
-    /// assert_eq!(result.get_billing_indices(),vec![
+    /// assert_eq!(result.get_billing_indices().unwrap(),vec![
     ///        "EASF01".to_string(),
     ///        "EASF02".to_string(),
     ///        "EASF03".to_string(),
     ///        "EASF04".to_string(),
     ///        "EASF05".to_string(),
     ///        "EASF06".to_string(),
-    ///        "EASF07".to_string(),
-    ///        "EASF08".to_string(),
-    ///        "EASF09".to_string(),
-    ///        "EASF10".to_string(),
     ///    ]);
     /// let mut stream = File::open("assets/stream_legacy_raw.txt").unwrap();
     /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
-    /// assert_eq!(result.get_billing_indices(),vec![
+    /// assert_eq!(result.get_billing_indices().unwrap(),vec![
     /// "BBRHCJB".to_string(),
     /// "BBRHPJB".to_string(),
     /// "BBRHCJR".to_string(),
@@ -180,51 +315,89 @@ impl TeleinfoMessage {
     /// "BBRHPJW".to_string(),
     /// ])
     /// ```
-    pub fn get_billing_indices(&self) -> Vec<String> {
+    pub fn get_billing_indices(&self) -> Result<Vec<String>, TeleinfoError> {
         match self.mode {
             TeleinfoMode::Legacy => self.get_billing_indices_legacy(),
             TeleinfoMode::Standard => self.get_billing_indices_standard(),
         }
     }
 
-    fn get_billing_indices_legacy(&self) -> Vec<String> {
-        let mut optarif = self
-            .get_value("OPTARIF".to_string())
-            .unwrap()
-            .value
-            .as_str();
-        if &optarif[0..3] == "BBR" {
-            optarif = "BBR";
-        }
-        match optarif {
-            "BASE" => vec!["BASE".to_string()],
-            "HC.." => vec!["HCHC".to_string(), "HCHP".to_string()],
-            "EJP." => vec!["EJPHN".to_string(), "EJPPM".to_string()],
-            "BBR" => vec![
-                "BBRHCJB".to_string(),
-                "BBRHPJB".to_string(),
-                "BBRHCJR".to_string(),
-                "BBRHPJR".to_string(),
-                "BBRHCJW".to_string(),
-                "BBRHPJW".to_string(),
-            ],
-            &_ => vec!["BASE".to_string()],
+    fn get_billing_indices_legacy(&self) -> Result<Vec<String>, TeleinfoError> {
+        Ok(self.get_tariff_option()?.legacy_indices())
+    }
+
+    fn get_billing_indices_standard(&self) -> Result<Vec<String>, TeleinfoError> {
+        let present: Vec<String> = (1..=10)
+            .map(|i| format!("EASF{:02}", i))
+            .filter(|label| self.values.contains_key(label))
+            .collect();
+        match self.get_supplier_indices() {
+            Ok(indices) => Ok(indices.into_iter().map(|index| index.label).collect()),
+            // without NGTF the contract is unknown, the indices sent by the meter are used
+            Err(TeleinfoError::MissingField { .. }) if !present.is_empty() => Ok(present),
+            Err(e) => Err(e),
         }
     }
 
-    fn get_billing_indices_standard(&self) -> Vec<String> {
-        vec![
-            "EASF01".to_string(),
-            "EASF02".to_string(),
-            "EASF03".to_string(),
-            "EASF04".to_string(),
-            "EASF05".to_string(),
-            "EASF06".to_string(),
-            "EASF07".to_string(),
-            "EASF08".to_string(),
-            "EASF09".to_string(),
-            "EASF10".to_string(),
-        ]
+    /// Return the supplier indices (EASF) used by the contract of a standard mode message,
+    /// named after their tariff periods. They are inferred from NGTF, NTARF with LTARF and the
+    /// PJOURF+1 schedule, `IndexHistory` completes them over several messages for contracts
+    /// unknown to the crate
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// let indices = result.get_supplier_indices().unwrap();
+    /// assert_eq!(indices.len(), 6);
+    /// assert_eq!(indices[2].label, "EASF03");
+    /// assert_eq!(indices[2].name, Some("HC BLANC".to_string()));
+    /// assert_eq!(indices[5].name, Some("HP ROUGE".to_string()));
+    /// ```
+    pub fn get_supplier_indices(&self) -> Result<Vec<BillingIndex>, TeleinfoError> {
+        self.get_mandatory_value("NGTF")?;
+        let mut history = IndexHistory::new();
+        history.push(self);
+        Ok(history.get_supplier_indices())
+    }
+
+    /// Return the distributor indices (EASD01 to EASD04) of a standard mode message, empty
+    /// in legacy mode. The meter does not send the distributor tariff periods, the indices are
+    /// not named
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// let indices = result.get_distributor_indices();
+    /// assert_eq!(indices.len(), 4);
+    /// assert_eq!(indices[0].label, "EASD01");
+    /// assert_eq!(indices[0].name, None);
+    /// ```
+    pub fn get_distributor_indices(&self) -> Vec<BillingIndex> {
+        billing::distributor_indices(self)
+    }
+
+    /// Return the billing indices of the injected energy, EAIT and the reactive indices of the
+    /// injection quadrants ERQ2 and ERQ3 sent by the meter, an error if the meter is not a
+    /// producer
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// assert!(result.get_export_billing_indices().is_err());
+    /// let producer = teleinfo_nom::parse_frame(
+    ///     "\nEAIT\t000012345\tT\r\nERQ1\t000000010\t<\r\nERQ2\t000000020\t>\r").unwrap();
+    /// assert_eq!(producer.get_export_billing_indices().unwrap(), vec!["EAIT", "ERQ2"]);
+    /// ```
+    pub fn get_export_billing_indices(&self) -> Result<Vec<String>, TeleinfoError> {
+        self.get_mandatory_value("EAIT")?;
+        Ok(["EAIT", "ERQ2", "ERQ3"]
+            .iter()
+            .filter(|label| self.values.contains_key(**label))
+            .map(|label| label.to_string())
+            .collect())
     }
 
     /// Return a &TeleinfoValue as Option for `key`
@@ -237,19 +410,331 @@ impl TeleinfoMessage {
     /// let mut stream = File::open("assets/stream_legacy_raw.txt").unwrap();
     /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
     /// assert_eq!(result.get_value("BBRHPJB".to_string()),Some(&teleinfo_nom::TeleinfoValue{value: "001012295".to_string(),
-    /// horodate: None }))
+    /// horodate: None, checksum: '>', expected_checksum: '>', valid: true, parity_error: false }))
     /// ```
     pub fn get_value(&self, key: String) -> Option<&TeleinfoValue> {
         self.values.get(&key)
     }
 
+    fn get_mandatory_value(&self, key: &str) -> Result<&TeleinfoValue, TeleinfoError> {
+        self.values
+            .get(key)
+            .ok_or_else(|| TeleinfoError::MissingField {
+                label: key.to_string(),
+            })
+    }
+
+    /// Return the decoded value of `key` as Option, with a FieldError if the value cannot be
+    /// decoded for its label
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// use teleinfo_nom::TeleinfoField;
+    /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// assert_eq!(result.get_field("EASF03".to_string()), Some(Ok(TeleinfoField::Energy(487131))));
+    /// assert_eq!(result.get_field("NGTF".to_string()),
+    ///            Some(Ok(TeleinfoField::Text("TEMPO".to_string()))));
+    /// assert_eq!(result.get_field("EAIT".to_string()), None);
+    /// ```
+    pub fn get_field(&self, key: String) -> Option<Result<TeleinfoField, FieldError>> {
+        self.values
+            .get_key_value(&key)
+            .map(|(k, v)| TeleinfoField::decode(k, &v.value))
+    }
+
+    /// Return a vector of tuples with (index,Option(decoded value)) from a vector of indices to
+    /// fetch
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// use teleinfo_nom::TeleinfoField;
+    /// let mut stream = File::open("assets/stream_legacy_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// assert_eq!(result.get_fields(vec!["BBRHCJB".to_string(), "PAPP".to_string()]),
+    ///            vec![
+    ///            ("BBRHCJB".to_string(), Some(Ok(TeleinfoField::Energy(1478389)))),
+    ///            ("PAPP".to_string(), Some(Ok(TeleinfoField::ApparentPower(120)))),
+    ///            ]);
+    /// ```
+    pub fn get_fields(
+        &self,
+        keys: Vec<String>,
+    ) -> Vec<(String, Option<Result<TeleinfoField, FieldError>>)> {
+        keys.into_iter()
+            .map(|idx| (idx.clone(), self.get_field(idx)))
+            .collect()
+    }
+
+    /// Return the energy index of `key` in Wh
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// let mut stream = File::open("assets/stream_legacy_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// assert_eq!(result.get_energy("BBRHPJB".to_string()), Some(Ok(1012295)));
+    /// assert!(result.get_energy("PTEC".to_string()).unwrap().is_err());
+    /// // decoding errors convert to TeleinfoError
+    /// fn current_energy(
+    ///     message: &teleinfo_nom::TeleinfoMessage,
+    /// ) -> Result<Option<u64>, teleinfo_nom::TeleinfoError> {
+    ///     let index = message.get_current_index()?;
+    ///     Ok(message.get_energy(index).transpose()?)
+    /// }
+    /// assert_eq!(current_energy(&result).unwrap(), Some(1012295));
+    /// let malformed = teleinfo_nom::parse_frame("\nPTEC HPJB P\r\nBBRHPJB 00101229X !\r").unwrap();
+    /// match current_energy(&malformed) {
+    ///     Err(teleinfo_nom::TeleinfoError::Field { source }) => assert_eq!(source.label, "BBRHPJB"),
+    ///     r => panic!("unexpected result {:?}", r),
+    /// }
+    /// ```
+    pub fn get_energy(&self, key: String) -> Option<Result<u64, FieldError>> {
+        self.get_typed(key, |f| match f {
+            TeleinfoField::Energy(x) => Some(*x),
+            _ => None,
+        })
+    }
+
+    /// Return the reactive energy index of `key` in VArh
+    pub fn get_reactive_energy(&self, key: String) -> Option<Result<u64, FieldError>> {
+        self.get_typed(key, |f| match f {
+            TeleinfoField::ReactiveEnergy(x) => Some(*x),
+            _ => None,
+        })
+    }
+
+    /// Return the apparent power of `key` in VA
+    pub fn get_apparent_power(&self, key: String) -> Option<Result<u32, FieldError>> {
+        self.get_typed(key, |f| match f {
+            TeleinfoField::ApparentPower(x) => Some(*x),
+            _ => None,
+        })
+    }
+
+    /// Return the current of `key` in A
+    pub fn get_current(&self, key: String) -> Option<Result<u16, FieldError>> {
+        self.get_typed(key, |f| match f {
+            TeleinfoField::Current(x) => Some(*x),
+            _ => None,
+        })
+    }
+
+    /// Return the voltage of `key` in V
+    pub fn get_voltage(&self, key: String) -> Option<Result<u16, FieldError>> {
+        self.get_typed(key, |f| match f {
+            TeleinfoField::Voltage(x) => Some(*x),
+            _ => None,
+        })
+    }
+
+    /// Return the trimmed text of `key`
+    pub fn get_text(&self, key: String) -> Option<Result<String, FieldError>> {
+        self.get_typed(key, |f| f.as_text().map(|x| x.to_string()))
+    }
+
+    fn get_typed<T, F>(&self, key: String, extract: F) -> Option<Result<T, FieldError>>
+    where
+        F: Fn(&TeleinfoField) -> Option<T>,
+    {
+        let value = self.values.get(&key)?;
+        Some(TeleinfoField::decode(&key, &value.value).and_then(|f| {
+            extract(&f).ok_or_else(|| FieldError {
+                label: key.clone(),
+                value: value.value.clone(),
+            })
+        }))
+    }
+
+    /// Return whether all datasets of the message were parsed and have a valid checksum,
+    /// without parity error
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Return the checksum method used to validate the message
+    pub fn get_checksum_method(&self) -> ChecksumMethod {
+        self.checksum_method
+    }
+
+    /// Return the labels of the message whose checksum is invalid, sorted
+    /// # Example
+    /// ```
+    /// let message = teleinfo_nom::parse_frame(
+    ///     "\nADCO 031961098836 M\r\nIINST 002 X\r\nPAPP 00120 $\r").unwrap();
+    /// assert_eq!(message.get_invalid_labels(), vec!["IINST".to_string()]);
+    /// assert_eq!(message.get_value("IINST".to_string()).unwrap().expected_checksum, 'Y');
+    /// ```
+    pub fn get_invalid_labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self
+            .values
+            .iter()
+            .filter(|(_, v)| !v.valid)
+            .map(|(k, _)| k.clone())
+            .collect();
+        labels.sort();
+        labels
+    }
+
+    /// Return the labels of the message received with a parity error, sorted
+    /// # Example
+    /// ```
+    /// let mut decoder = teleinfo_nom::TeleinfoDecoder::new().parity(true);
+    /// // 7E1 bytes read as 8 bits, the 'A' of PAPP has a wrong parity bit
+    /// let even = |b: &u8| b | ((b.count_ones() % 2) as u8) << 7;
+    /// let mut frame: Vec<u8> = b"\x02\nADCO 031961098836 M\r\nPAPP 00120 $\r\x03"
+    ///     .iter()
+    ///     .map(even)
+    ///     .collect();
+    /// frame[24] ^= 0x80;
+    /// let message = decoder.feed(&frame).pop().unwrap().unwrap();
+    /// assert_eq!(message.get_parity_error_labels(), vec!["PAPP".to_string()]);
+    /// assert!(message.get_invalid_labels().is_empty());
+    /// assert!(!message.is_valid());
+    /// ```
+    pub fn get_parity_error_labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self
+            .values
+            .iter()
+            .filter(|(_, v)| v.parity_error)
+            .map(|(k, _)| k.clone())
+            .collect();
+        labels.sort();
+        labels
+    }
+
+    fn flag_parity_errors(&mut self, labels: &[String]) {
+        self.valid = false;
+        for label in labels {
+            if let Some(value) = self.values.get_mut(label) {
+                value.parity_error = true;
+            }
+        }
+    }
+
+    /// Return the labels of the message not defined in the specification for its mode, sorted
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// assert!(result.get_unknown_labels().is_empty());
+    /// ```
+    pub fn get_unknown_labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self
+            .values
+            .keys()
+            .filter(|k| parser::label_kind(self.mode, k) == parser::LabelKind::Unknown)
+            .cloned()
+            .collect();
+        labels.sort();
+        labels
+    }
+
+    /// Return the decoded STGE status register as Option, None in legacy mode or if the
+    /// register is missing or malformed
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// let status = result.get_status().unwrap();
+    /// assert_eq!(status.supplier_index, 3);
+    /// assert_eq!(status.tempo_today, Some(teleinfo_nom::TempoColor::White));
+    /// assert_eq!(status.tempo_tomorrow, Some(teleinfo_nom::TempoColor::Blue));
+    /// let legacy = teleinfo_nom::parse_frame("\nSTGE 463A0800 Y\r").unwrap();
+    /// assert!(legacy.get_status().is_none());
+    /// ```
+    pub fn get_status(&self) -> Option<TeleinfoStatus> {
+        if self.mode != TeleinfoMode::Standard {
+            return None;
+        }
+        self.get_value("STGE".to_string())
+            .and_then(|v| TeleinfoStatus::from_hex(&v.value))
+    }
+
+    /// Return the decoded PPOT register of a legacy three-phase message as Option, None if
+    /// the register is missing or malformed
+    /// # Example
+    /// ```
+    /// let message = teleinfo_nom::parse_frame(
+    ///     "\nADCO 031961098836 M\r\nPPOT 0A 4\r\nMOTDETAT 000000 B\r").unwrap();
+    /// let presence = message.get_phase_presence().unwrap();
+    /// assert_eq!(presence.get_lost_phases(), vec![1, 3]);
+    /// assert!(message.get_meter_state().unwrap().is_nominal());
+    /// ```
+    pub fn get_phase_presence(&self) -> Option<PhasePresence> {
+        self.get_value("PPOT".to_string())
+            .and_then(|v| PhasePresence::from_hex(&v.value))
+    }
+
+    /// Return the decoded MOTDETAT register of a legacy message as Option, None if the
+    /// register is missing or malformed
+    pub fn get_meter_state(&self) -> Option<MeterState> {
+        self.get_value("MOTDETAT".to_string())
+            .and_then(|v| MeterState::from_hex(&v.value))
+    }
+
+    /// Return the decoded RELAIS register of a standard message as Option, None if the
+    /// register is missing or malformed
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// assert!(result.get_relay_states().unwrap().dry_contact());
+    /// ```
+    pub fn get_relay_states(&self) -> Option<RelayStates> {
+        self.get_value("RELAIS".to_string())
+            .and_then(|v| RelayStates::from_decimal(&v.value))
+    }
+
+    /// Return the relays switched since the `previous` message, empty if either message has
+    /// no RELAIS register
+    /// # Example
+    /// ```
+    /// let before = teleinfo_nom::parse_frame("\nRELAIS\t000\tB\r").unwrap();
+    /// let after = teleinfo_nom::parse_frame("\nRELAIS\t001\tC\r").unwrap();
+    /// let changes = after.get_relay_changes(&before);
+    /// assert_eq!(changes, vec![teleinfo_nom::RelayChange { relay: 1, closed: true }]);
+    /// ```
+    pub fn get_relay_changes(&self, previous: &TeleinfoMessage) -> Vec<RelayChange> {
+        match (self.get_relay_states(), previous.get_relay_states()) {
+            (Some(now), Some(before)) => now.changes(&before),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Return the decoded PJOURF+1 schedule of the next day in standard mode as Option, None
+    /// if the dataset is missing or malformed
+    /// # Example
+    /// ```
+    /// use std::fs::File;
+    /// use chrono::NaiveTime;
+    /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
+    /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
+    /// let schedule = result.get_next_day_schedule().unwrap();
+    /// assert_eq!(schedule.slots.len(), 3);
+    /// assert_eq!(schedule.index_at(NaiveTime::from_hms_opt(7, 0, 0).unwrap()), Some(2));
+    /// ```
+    pub fn get_next_day_schedule(&self) -> Option<DaySchedule> {
+        self.get_value("PJOURF+1".to_string())
+            .and_then(|v| DaySchedule::parse(&v.value))
+    }
+
+    /// Return the decoded PPOINTE schedule of the next peak day in standard mode as Option,
+    /// None if the dataset is missing or malformed
+    pub fn get_peak_day_schedule(&self) -> Option<DaySchedule> {
+        self.get_value("PPOINTE".to_string())
+            .and_then(|v| DaySchedule::parse(&v.value))
+    }
+
     /// Return a vector of tuples with (index,Option(value)) from a vector of indices to fetch
     /// # Example
     /// ```
     /// use std::fs::File;
     /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
     /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
-    /// assert_eq!(result.get_values(result.get_billing_indices()),
+    /// assert_eq!(result.get_values(result.get_billing_indices().unwrap()),
     ///            vec![
     ///            ("EASF01".to_string(),Some("004855593".to_string())),
     ///            ("EASF02".to_string(),Some("014090959".to_string())),
@@ -257,14 +742,10 @@ impl TeleinfoMessage {
     ///            ("EASF04".to_string(),Some("001481464".to_string())),
     ///            ("EASF05".to_string(),Some("000227596".to_string())),
     ///            ("EASF06".to_string(),Some("000706363".to_string())),
-    ///            ("EASF07".to_string(),Some("000000000".to_string())),
-    ///            ("EASF08".to_string(),Some("000000000".to_string())),
-    ///            ("EASF09".to_string(),Some("000000000".to_string())),
-    ///            ("EASF10".to_string(),Some("000000000".to_string())),
     ///            ]);
     /// let mut stream = File::open("assets/stream_legacy_raw.txt").unwrap();
     /// let (remain, result) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
-    /// assert_eq!(result.get_values(result.get_billing_indices()),
+    /// assert_eq!(result.get_values(result.get_billing_indices().unwrap()),
     ///            vec![
     ///            ("BBRHCJB".to_string(),Some("001478389".to_string())),
     ///            ("BBRHPJB".to_string(),Some("001012295".to_string())),
@@ -275,51 +756,157 @@ impl TeleinfoMessage {
     ///            ]);
     pub fn get_values(&self, keys: Vec<String>) -> Vec<(String, Option<String>)> {
         keys.into_iter()
-            .map(|idx| (idx.clone(), get_value_from_teleinfovalue(self.get_value(idx))))
+            .map(|idx| {
+                (
+                    idx.clone(),
+                    get_value_from_teleinfovalue(self.get_value(idx)),
+                )
+            })
             .collect()
     }
 }
 
+mod alert;
+mod billing;
+#[cfg(feature = "codec")]
+mod codec;
+mod decoder;
+mod detect;
+mod encoder;
+mod error;
+mod field;
+mod frame;
+mod horodate;
+#[cfg(any(test, feature = "simulator"))]
+mod noise;
 pub mod parser;
+mod reactive;
+mod reader;
+mod schedule;
+#[cfg(any(test, feature = "simulator"))]
+mod simulator;
+mod status;
+#[cfg(feature = "async")]
+mod stream;
+mod tariff;
+
+pub use alert::{Overcurrent, OvercurrentAlert};
+pub use billing::{BillingIndex, IndexHistory};
+#[cfg(feature = "codec")]
+pub use codec::TeleinfoCodec;
+pub use decoder::TeleinfoDecoder;
+pub use detect::{baud_rate, detect, Detection, Recommendation};
+pub use encoder::{encode_message, format_frame};
+pub use error::TeleinfoError;
+pub use field::{FieldError, TeleinfoField};
+pub use frame::{TeleinfoParser, Validation};
+pub use horodate::{Season, TeleinfoDate};
+#[cfg(any(test, feature = "simulator"))]
+pub use noise::NoisyReader;
+pub use parser::ChecksumMethod;
+pub use reactive::{ReactiveAnalyser, ReactiveReport};
+pub use reader::TeleinfoReader;
+pub use schedule::{DaySchedule, RelayAction, TimeSlot};
+#[cfg(any(test, feature = "simulator"))]
+pub use simulator::{Contract, LoadProfile, TariffCalendar, TeleinfoSimulator};
+pub use status::{
+    CplStatus, CutOffState, EuridisStatus, MeterState, MobilePeak, PhasePresence, RelayChange,
+    RelayStates, TeleinfoStatus, TempoColor,
+};
+#[cfg(feature = "async")]
+pub use stream::TeleinfoStream;
+pub use tariff::{TariffOption, TariffPeriod};
 
 fn get_value_from_teleinfovalue(value: Option<&TeleinfoValue>) -> Option<String> {
-    match value {
-        Some(x) => Some(x.value.clone()),
-        None => None,
-    }
+    value.map(|x| x.value.clone())
 }
 
-fn parsed_vector_to_values(lines: Vec<TeleinfoTuple>) -> HashMap<String, TeleinfoValue> {
+fn parsed_vector_to_values(
+    mode: TeleinfoMode,
+    method: ChecksumMethod,
+    lines: Vec<TeleinfoTuple<'_>>,
+) -> HashMap<String, TeleinfoValue> {
     let mut values = HashMap::new();
     for line in lines {
-        match line {
-            (key, val, _, hd) => values.insert(
-                key.to_string(),
-                TeleinfoValue {
-                    value: val.to_string(),
-                    horodate: hd,
-                },
-            ),
-        };
+        let expected_checksum = parser::expected_checksum(method, mode, &line);
+        let (key, val, checksum, hd) = line;
+        values.insert(
+            key.to_string(),
+            TeleinfoValue {
+                value: val.to_string(),
+                horodate: hd,
+                checksum,
+                expected_checksum,
+                valid: checksum == expected_checksum,
+                parity_error: false,
+            },
+        );
     }
     values
 }
 
-fn build_message(raw_message: &str) -> Result<TeleinfoMessage> {
-    let (r, (lines, mode)) = parser::parser_message(raw_message).unwrap();
-    let mut result = TeleinfoMessage {
-        values: HashMap::new(),
-        mode,
-        valid: true,
-    };
-    result.valid = r.is_empty() && parser::validate_message(mode, lines.clone());
-    result.values = parsed_vector_to_values(lines);
-    Ok(result)
+/// Parse `frame`, the content of a message between its STX and ETX characters.
+/// Parsing stops at the first malformed dataset, the message is then marked as invalid.
+/// An error is returned only if no dataset can be parsed.
+/// The checksum method is detected anew for each frame, a `TeleinfoParser`, `TeleinfoDecoder`
+/// or `TeleinfoReader` keeps the method detected from the first frames of a stream
+/// # Example
+/// ```
+/// let message = teleinfo_nom::parse_frame("\nADCO 031961098836 M\r\nPAPP 00120 $\r").unwrap();
+/// assert_eq!(message.get_value("PAPP".to_string()).unwrap().value, "00120");
+/// assert!(teleinfo_nom::parse_frame("\nADCO\r").is_err());
+/// ```
+pub fn parse_frame(frame: &str) -> Result<TeleinfoMessage, TeleinfoError> {
+    TeleinfoParser::new()
+        .validation(Validation::Partial)
+        .parse(frame)
+}
+
+/// Parse `frame` like `parse_frame` but return an error for the first malformed dataset,
+/// unknown label or bad checksum
+/// # Example
+/// ```
+/// use teleinfo_nom::TeleinfoError;
+/// assert!(teleinfo_nom::parse_frame_strict("\nADCO 031961098836 M\r\nPAPP 00120 $\r").is_ok());
+/// match teleinfo_nom::parse_frame_strict("\nADCO 031961098836 M\r\nPAPP 00120 #\r") {
+///     Err(TeleinfoError::Checksum { offset, line, expected, .. }) => {
+///         assert_eq!(offset, 21);
+///         assert_eq!(line, "PAPP 00120 #");
+///         assert_eq!(expected, '$');
+///     }
+///     _ => panic!("checksum error expected"),
+/// }
+/// ```
+pub fn parse_frame_strict(frame: &str) -> Result<TeleinfoMessage, TeleinfoError> {
+    TeleinfoParser::new()
+        .validation(Validation::Strict)
+        .parse(frame)
+}
+
+/// Parse `frame` like `parse_frame` but keep only the datasets which are well formed and
+/// whose checksum is valid, skipping the others.
+/// An error is returned only if no dataset can be kept
+/// # Example
+/// ```
+/// let message = teleinfo_nom::parse_frame_lenient(
+///     "\nADCO 031961098836 M\r\nIINST 0@1 X\r\nPAPP 00120 $\r").unwrap();
+/// assert_eq!(message.get_value("IINST".to_string()), None);
+/// assert_eq!(message.get_value("PAPP".to_string()).unwrap().value, "00120");
+/// assert!(!message.is_valid());
+/// ```
+pub fn parse_frame_lenient(frame: &str) -> Result<TeleinfoMessage, TeleinfoError> {
+    TeleinfoParser::new()
+        .validation(Validation::Lenient)
+        .parse(frame)
 }
 
 /// Read message from an readable object `source`, with `leftover` being the unparsed string
 /// from a previous call
-/// Returns a tuple with to be parsed in a next call string as `leftover` and the first found TeleinfoMessage
+/// Returns a tuple with to be parsed in a next call string as `leftover` and the first found TeleinfoMessage.
+/// A message interrupted by an EOT character is skipped and reading resumes at the next STX,
+/// the message sent after it, usually a short frame, is returned. `read_message` reports the
+/// interrupted message as an Interrupted error.
+/// The checksum method is detected anew on each call, `read_message` keeps it between calls
 /// # Example
 /// ```
 /// use std::fs::File;
@@ -327,44 +914,121 @@ fn build_message(raw_message: &str) -> Result<TeleinfoMessage> {
 /// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
 /// let (remain, msg1) = teleinfo_nom::get_message(&mut stream, "".to_string()).unwrap();
 /// ```
-pub fn get_message<T: Read>(source: &mut T, leftover: String) -> Result<(String, TeleinfoMessage)> {
-    let mut acc: Vec<u8> = Vec::with_capacity(2000);
-    //let mut buf: Vec<u8> = Vec::with_capacity(200);
-    let mut leftover = leftover.as_bytes().to_vec();
-    acc.append(&mut leftover);
+pub fn get_message<T: Read>(
+    source: &mut T,
+    leftover: String,
+) -> Result<(String, TeleinfoMessage), TeleinfoError> {
+    let mut leftover = leftover;
+    let mut parser = TeleinfoParser::new();
+    loop {
+        match read_message(source, &mut leftover, &mut parser) {
+            Err(TeleinfoError::Interrupted { .. }) => (),
+            result => return result.map(|message| (leftover, message)),
+        }
+    }
+}
+
+/// Read the next message from `source` like `get_message`, with `parser` parsing the frames
+/// so the checksum method it detects carries over from call to call. `leftover` holds the
+/// data read and not yet decoded, it is updated in place even when an error is returned.
+/// The data is decoded by a `TeleinfoDecoder` of default capacity which scans each byte read
+/// once, each of its results is returned by a call: the data of a truncated message comes as a
+/// framing error before the message sent after it
+/// # Example
+/// ```
+/// use std::fs::File;
+/// use teleinfo_nom::{ChecksumMethod, TeleinfoParser};
+/// let mut stream = File::open("assets/stream_standard_raw.txt").unwrap();
+/// let mut parser = TeleinfoParser::new();
+/// let mut leftover = String::new();
+/// let message = teleinfo_nom::read_message(&mut stream, &mut leftover, &mut parser).unwrap();
+/// assert!(message.is_valid());
+/// assert_eq!(parser.get_checksum_method(), Some(ChecksumMethod::WithSeparator));
+/// assert!(leftover.starts_with("\u{2}\nADSC"));
+/// ```
+pub fn read_message<T: Read>(
+    source: &mut T,
+    leftover: &mut String,
+    parser: &mut TeleinfoParser,
+) -> Result<TeleinfoMessage, TeleinfoError> {
+    let mut decoder = TeleinfoDecoder::new().parser(parser.clone());
+    let mut pending = std::mem::take(leftover).into_bytes();
+    let result = next_message(source, &mut decoder, &mut pending);
+    *parser = decoder.get_parser().clone();
+    // the decoder holds the start of a message, `pending` the data after a message
+    let mut rest = decoder.get_pending().to_vec();
+    rest.append(&mut pending);
+    *leftover = String::from_utf8_lossy(&rest).into_owned();
+    result
+}
+
+fn next_message<T: Read>(
+    source: &mut T,
+    decoder: &mut TeleinfoDecoder,
+    pending: &mut Vec<u8>,
+) -> Result<TeleinfoMessage, TeleinfoError> {
+    let mut eof = false;
     loop {
+        // feed the decoder up to each end of frame so the data after the first message stays
+        // in `pending`
+        while let Some(end) = pending.iter().position(|&b| b == ETX || b == EOT) {
+            let rest = pending.split_off(end + 1);
+            let frame = std::mem::replace(pending, rest);
+            let mut results = decoder.feed(&frame).into_iter();
+            if let Some(result) = results.next() {
+                if results.next().is_some() {
+                    // the data of a truncated message comes first, the message starting at the
+                    // last STX is decoded again by the next call
+                    let start = frame.iter().rposition(|&b| b == STX).unwrap_or(0);
+                    pending.splice(..0, frame[start..].iter().copied());
+                }
+                return result;
+            }
+        }
+        let results = decoder.feed(pending);
+        pending.clear();
+        // the data dropped when no message ends within the capacity of the decoder
+        if let Some(result) = results.into_iter().next() {
+            return result;
+        }
+        if eof {
+            return Err(TeleinfoError::Io {
+                offset: decoder.get_pending().len(),
+                source: io::Error::new(io::ErrorKind::UnexpectedEof, "no complete message"),
+            });
+        }
         let mut buf: Vec<u8> = vec![0; 200];
-        buf = match source.read(buf.as_mut_slice()) {
-            Ok(t) => buf[..t].to_vec(),
-            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => vec![],
-            Err(e) => return Err(e),
-        };
-        acc.append(&mut buf);
-        let current_clone = acc.clone();
-        let current_data = String::from_utf8_lossy(&current_clone);
-        match parser::get_message(&current_data) {
-            Ok((r, message)) => {
-                let result = build_message(message).unwrap();
-                return Ok((r.to_string(), result));
+        match source.read(buf.as_mut_slice()) {
+            Ok(0) => eof = true,
+            Ok(t) => pending.extend_from_slice(&buf[..t]),
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => (),
+            Err(e) => {
+                return Err(TeleinfoError::Io {
+                    offset: decoder.get_pending().len(),
+                    source: e,
+                })
             }
-            Err(nom::Err::Incomplete(_)) => (),
-            Err(_) => return handle_nom_error(),
         };
     }
 }
 
-fn handle_nom_error() -> Result<(String, TeleinfoMessage)> {
-    Err(Error::new(ErrorKind::InvalidData, "Parse Error"))
-}
-
 #[cfg(test)]
 mod tests {
     use crate::get_message;
-    use crate::parsed_vector_to_values;
+    use crate::parse_frame;
+    use crate::parse_frame_strict;
+    use crate::read_message;
+    use crate::simulator::fixture::{at, decode};
+    use crate::ChecksumMethod;
+    use crate::Season;
     use crate::TeleinfoDate;
+    use crate::TeleinfoError;
     use crate::TeleinfoMessage;
+    use crate::TeleinfoMessageType;
     use crate::TeleinfoMode;
-    use chrono::{Local, TimeZone};
+    use crate::TeleinfoParser;
+    use crate::{LoadProfile, TeleinfoMeterRole, TeleinfoSimulator};
+    use chrono::TimeZone;
     use std::fs::File;
     #[test]
     fn test_get_message() {
@@ -372,7 +1036,7 @@ mod tests {
         let expect_values = vec![
             ("ADSC","041776199277",'I',None),
             ("VTIC","02",'J',None),
-            ("DATE","",';',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(23, 8, 4), raw_value: "H200214230804".to_string() })),
+            ("DATE","",';',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 23, 8, 4).unwrap(), raw_value: "H200214230804".to_string() })),
             ("NGTF","     TEMPO      ",'F',None),
             ("LTARF","   HC  BLANC    ",'6',None),
             ("EAST","021849106",'.',None),
@@ -402,22 +1066,22 @@ mod tests {
             ("SINSTS1","00664",'G',None),
             ("SINSTS2","01373",'F',None),
             ("SINSTS3","00664",'I',None),
-            ("SMAXSN","10802",'7',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(17, 51, 35), raw_value: "H200214175135".to_string() })),
-            ("SMAXSN1","03411",'&',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(17, 51, 35), raw_value: "H200214175135".to_string() })),
-            ("SMAXSN2","03899",';',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(17, 51, 35), raw_value: "H200214175135".to_string() })),
-            ("SMAXSN3","03512",'*',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(17, 51, 35), raw_value: "H200214175135".to_string() })),
-            ("SMAXSN-1","09562",' ',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 13).and_hms(8, 51, 18), raw_value: "H200213085118".to_string() })),
-            ("SMAXSN1-1","03129",'J',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 13).and_hms(8, 51, 18), raw_value: "H200213085118".to_string() })),
-            ("SMAXSN2-1","03366",'@',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 13).and_hms(10, 11, 42), raw_value: "H200213101142".to_string() })),
-            ("SMAXSN3-1","03191",'K',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 13).and_hms(8, 51, 18), raw_value: "H200213085118".to_string() })), 
-            ("CCASN","01650",'5',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(23, 0, 0), raw_value: "H200214230000".to_string() })),
-            ("CCASN-1","00786",' ',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(22, 50, 0), raw_value: "H200214225000".to_string() })),
-            ("UMOY1","237",'(',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(23, 0, 0), raw_value: "H200214230000".to_string() })),
-            ("UMOY2","238",'*',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(23, 0, 0), raw_value: "H200214230000".to_string() })),
-            ("UMOY3","236",')',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(23, 0, 0), raw_value: "H200214230000".to_string() })),
+            ("SMAXSN","10802",'7',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 17, 51, 35).unwrap(), raw_value: "H200214175135".to_string() })),
+            ("SMAXSN1","03411",'&',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 17, 51, 35).unwrap(), raw_value: "H200214175135".to_string() })),
+            ("SMAXSN2","03899",';',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 17, 51, 35).unwrap(), raw_value: "H200214175135".to_string() })),
+            ("SMAXSN3","03512",'*',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 17, 51, 35).unwrap(), raw_value: "H200214175135".to_string() })),
+            ("SMAXSN-1","09562",' ',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 13, 8, 51, 18).unwrap(), raw_value: "H200213085118".to_string() })),
+            ("SMAXSN1-1","03129",'J',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 13, 8, 51, 18).unwrap(), raw_value: "H200213085118".to_string() })),
+            ("SMAXSN2-1","03366",'@',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 13, 10, 11, 42).unwrap(), raw_value: "H200213101142".to_string() })),
+            ("SMAXSN3-1","03191",'K',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 13, 8, 51, 18).unwrap(), raw_value: "H200213085118".to_string() })), 
+            ("CCASN","01650",'5',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 23, 0, 0).unwrap(), raw_value: "H200214230000".to_string() })),
+            ("CCASN-1","00786",' ',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 22, 50, 0).unwrap(), raw_value: "H200214225000".to_string() })),
+            ("UMOY1","237",'(',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 23, 0, 0).unwrap(), raw_value: "H200214230000".to_string() })),
+            ("UMOY2","238",'*',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 23, 0, 0).unwrap(), raw_value: "H200214230000".to_string() })),
+            ("UMOY3","236",')',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 23, 0, 0).unwrap(), raw_value: "H200214230000".to_string() })),
             ("STGE","463A0800",'K',None),
-            ("DPM1","00",'\\',Some(TeleinfoDate { season: ' ', date: Local.ymd(2020, 2, 14).and_hms(6, 0, 0), raw_value: " 200214060000".to_string() })),
-            ("FPM1","00",'_',Some(TeleinfoDate { season: ' ', date: Local.ymd(2020, 2, 15).and_hms(6, 0, 0), raw_value: " 200215060000".to_string() })),
+            ("DPM1","00",'\\',Some(TeleinfoDate { season: ' ', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 6, 0, 0).unwrap(), raw_value: " 200214060000".to_string() })),
+            ("FPM1","00",'_',Some(TeleinfoDate { season: ' ', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 15, 6, 0, 0).unwrap(), raw_value: " 200215060000".to_string() })),
             ("MSG1","PAS DE          MESSAGE         ",'<',None),
             ("PRM","07361794479930",'F',None),
             ("RELAIS","001",'C',None),
@@ -425,11 +1089,12 @@ mod tests {
             ("NJOURF","00",'&',None),
             ("NJOURF+1","00",'B',None),
             ("PJOURF+1","00004001 06004002 22004001 NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE",'.',None)];
-        let expect = TeleinfoMessage {
-            values: parsed_vector_to_values(expect_values),
-            mode: TeleinfoMode::Standard,
-            valid: true,
-        };
+        let expect = TeleinfoMessage::from_datasets(
+            TeleinfoMode::Standard,
+            ChecksumMethod::WithSeparator,
+            true,
+            expect_values,
+        );
         let expect_values_inc = vec![
             ("ADSC", "041776199277", 'I', None),
             ("VTIC", "02", 'J', None),
@@ -439,7 +1104,10 @@ mod tests {
                 '=',
                 Some(TeleinfoDate {
                     season: 'H',
-                    date: Local.ymd(2020, 2, 14).and_hms(23, 8, 6),
+                    date: Season::Winter
+                        .offset()
+                        .with_ymd_and_hms(2020, 2, 14, 23, 8, 6)
+                        .unwrap(),
                     raw_value: "H200214230806".to_string(),
                 }),
             ),
@@ -457,11 +1125,12 @@ mod tests {
             ("EASF09", "000000000", '*', None),
             ("EASF10", "000000000", '"', None),
         ];
-        let expect_inc = TeleinfoMessage {
-            values: parsed_vector_to_values(expect_values_inc),
-            mode: TeleinfoMode::Standard,
-            valid: false,
-        };
+        let expect_inc = TeleinfoMessage::from_datasets(
+            TeleinfoMode::Standard,
+            ChecksumMethod::WithSeparator,
+            false,
+            expect_values_inc,
+        );
         let (remain, result) = get_message(&mut stream, "".to_string()).unwrap();
         assert_eq!( (remain.clone(),result) ,
  ("\u{2}\nADSC\t041776199277\tI\r\nVTIC\t02\tJ\r\nDATE\tH200214230806\t\t=\r\nNGTF\t     TEMPO      \tF\r\nLTARF\t   HC  BLANC    \t6\r\nEAST\t021849107\t/\r\nEASF01\t004855593\tI\r\nEASF02\t014".to_string(),expect));
@@ -469,4 +1138,167 @@ mod tests {
         assert_eq!( (remain2,result2) ,
  ("\u{2}\nADSC\t041776199277\tI\r\nVTIC\t02\tJ\r\nDATE\tH200214230807\t\t>\r\nNGTF\t     TEMPO      \tF\r\nLTARF\t   H".to_string(),expect_inc));
     }
+    #[test]
+    fn test_unknown_labels() {
+        let message =
+            parse_frame("\nADCO 031961098836 M\r\nIINST1 001 I\r\nNEWLABEL 12 1\r\nPAPP 00120 $\r")
+                .unwrap();
+        assert_eq!(message.values.len(), 4);
+        assert_eq!(message.get_unknown_labels(), vec!["NEWLABEL".to_string()]);
+    }
+    #[test]
+    fn test_errors() {
+        let message = parse_frame("\nIINST1 001 I\r").unwrap();
+        match message.get_current_index() {
+            Err(TeleinfoError::MissingField { label }) => assert_eq!(label, "PTEC"),
+            r => panic!("unexpected result {:?}", r),
+        }
+        match message.get_billing_indices() {
+            Err(TeleinfoError::MissingField { label }) => assert_eq!(label, "OPTARIF"),
+            r => panic!("unexpected result {:?}", r),
+        }
+        match parse_frame_strict("\nADCO 031961098836 M\r\nNEWLABEL 12 1\r") {
+            Err(TeleinfoError::UnknownLabel { offset, line }) => {
+                assert_eq!(offset, 21);
+                assert_eq!(line, "NEWLABEL 12 1");
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        match parse_frame_strict("\nADCO 031961098836 M\r\nEASF01\t004855593\tI\r") {
+            Err(TeleinfoError::ModeMismatch { offset, .. }) => assert_eq!(offset, 21),
+            r => panic!("unexpected result {:?}", r),
+        }
+        match parse_frame("\nSMAXSN\tH201314175135\t10802\t7\r") {
+            Err(TeleinfoError::InvalidHorodate { offset, .. }) => assert_eq!(offset, 0),
+            r => panic!("unexpected result {:?}", r),
+        }
+        let message = parse_frame("\nOPTARIF XXXX 5\r\nPTEC XXXX L\r").unwrap();
+        match message.get_billing_indices() {
+            Err(TeleinfoError::UnknownTariff { label, value }) => {
+                assert_eq!((label.as_str(), value.as_str()), ("OPTARIF", "XXXX"))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        match message.get_current_index() {
+            Err(TeleinfoError::UnknownTariff { label, .. }) => assert_eq!(label, "PTEC"),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+    #[test]
+    fn test_get_message_interrupted() {
+        let data = "\u{2}\nADCO 031961098836 M\r\nOPT\u{4}\
+                    \u{2}\nADIR1 063 *\r\nADCO 031961098836 M\r\nIINST1 063 Q\r\u{3}";
+        // the short frame sent after the interrupted message is returned
+        let mut stream = data.as_bytes();
+        let (remain, message) = get_message(&mut stream, String::new()).unwrap();
+        assert!(remain.is_empty());
+        assert!(message.is_valid());
+        assert_eq!(message.get_message_type(), TeleinfoMessageType::Short);
+        assert_eq!(message.get_value("ADIR1".to_string()).unwrap().value, "063");
+        // the message after the interrupted one is kept in leftover
+        let mut stream = data.as_bytes();
+        let mut leftover = String::new();
+        let mut parser = TeleinfoParser::new();
+        match read_message(&mut stream, &mut leftover, &mut parser) {
+            Err(TeleinfoError::Interrupted { offset, partial }) => {
+                assert_eq!(offset, 0);
+                assert_eq!(partial, "\nADCO 031961098836 M\r\nOPT");
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(leftover.starts_with("\u{2}\nADIR1"));
+        let message = read_message(&mut stream, &mut leftover, &mut parser).unwrap();
+        assert_eq!(message.get_message_type(), TeleinfoMessageType::Short);
+        assert!(leftover.is_empty());
+    }
+    #[test]
+    fn test_read_message_truncated() {
+        // the truncated message is reported before the message sent after it
+        let data = "ADCO 0319\u{2}\nADCO 031961098836 M\r\u{3}";
+        let mut stream = data.as_bytes();
+        let mut leftover = String::new();
+        let mut parser = TeleinfoParser::new();
+        match read_message(&mut stream, &mut leftover, &mut parser) {
+            Err(TeleinfoError::Framing { offset, line }) => {
+                assert_eq!(offset, 0);
+                assert_eq!(line, "ADCO 0319");
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        let message = read_message(&mut stream, &mut leftover, &mut parser).unwrap();
+        assert!(message.get_value("ADCO".to_string()).is_some());
+        assert!(leftover.is_empty());
+    }
+    #[test]
+    fn test_ejp_indices() {
+        // the peak index of legacy EJP meters is sent as EJPHPM
+        let message = parse_frame(concat!(
+            "\nOPTARIF EJP. \"\r\nEJPHN 000123456 :\r",
+            "\nEJPHPM 000001234 >\r\nPTEC PM.. %\r"
+        ))
+        .unwrap();
+        assert!(message.is_valid());
+        assert_eq!(
+            message.get_billing_indices().unwrap(),
+            vec!["EJPHN", "EJPHPM"]
+        );
+        assert_eq!(message.get_current_index().unwrap(), "EJPHPM");
+        assert_eq!(
+            message.get_energy(message.get_current_index().unwrap()),
+            Some(Ok(1234))
+        );
+    }
+    #[test]
+    fn test_billing_indices_without_ngtf() {
+        let message = parse_frame("\nEASF01\t004855593\tI\r\nEASF02\t014090959\tH\r").unwrap();
+        assert!(message.is_valid());
+        assert_eq!(
+            message.get_billing_indices().unwrap(),
+            vec!["EASF01", "EASF02"]
+        );
+        match message.get_supplier_indices() {
+            Err(TeleinfoError::MissingField { label }) => assert_eq!(label, "NGTF"),
+            r => panic!("unexpected result {:?}", r),
+        }
+        let message = parse_frame("\nPAPP 00120 $\r").unwrap();
+        assert!(message.get_billing_indices().is_err());
+    }
+    #[test]
+    fn test_get_message_eof() {
+        let mut stream = File::open("assets/stream_legacy_raw.txt").unwrap();
+        let (remain, _) = get_message(&mut stream, "".to_string()).unwrap();
+        match get_message(&mut stream, remain) {
+            Err(TeleinfoError::Io { source, .. }) => {
+                assert_eq!(source.kind(), std::io::ErrorKind::UnexpectedEof)
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+    #[test]
+    fn test_producer() {
+        for mode in [TeleinfoMode::Legacy, TeleinfoMode::Standard].iter() {
+            let meter = TeleinfoSimulator::new(*mode, at(2020, 7, 14, 12))
+                .load(LoadProfile::new(vec![500]))
+                .production(LoadProfile::solar(3000));
+            let message = decode(&meter);
+            match mode {
+                TeleinfoMode::Legacy => {
+                    assert_eq!(message.get_meter_role(), TeleinfoMeterRole::Consumer);
+                    assert!(message.get_injected_power().is_none());
+                    assert!(message.get_export_billing_indices().is_err());
+                }
+                TeleinfoMode::Standard => {
+                    assert_eq!(message.get_meter_role(), TeleinfoMeterRole::Producer);
+                    let injected = message.get_injected_power().unwrap().unwrap();
+                    assert!(injected > 0);
+                    assert!(message.get_net_power().unwrap() < 0);
+                    assert_eq!(
+                        message.get_export_billing_indices().unwrap(),
+                        vec!["EAIT".to_string()]
+                    );
+                    assert!(message.get_injected_energy().unwrap().is_ok());
+                }
+            }
+        }
+    }
 }
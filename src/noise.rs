@@ -0,0 +1,259 @@
+//! Fault injection in a readable source, to test the behaviour on a noisy line
+
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+const STX: u8 = 0x02;
+
+/// NoisyReader reads `source` and corrupts its data like a long or disturbed teleinfo line.
+/// Rates are probabilities from 0 to 1: bit flips, drops and duplications apply to each
+/// byte, truncations to each frame which is then cut at a random length up to the next STX.
+/// The corruption only depends on the seed and the data, so failures can be replayed
+/// # Example
+/// ```
+/// use std::fs::File;
+/// let capture = File::open("assets/stream_standard_raw.txt").unwrap();
+/// let noisy = teleinfo_nom::NoisyReader::new(capture, 42).bit_flip(0.001).byte_drop(0.001);
+/// for message in teleinfo_nom::TeleinfoReader::new(noisy).skip_invalid(true) {
+///     assert!(message.unwrap().is_valid());
+/// }
+/// ```
+pub struct NoisyReader<R: Read> {
+    source: R,
+    state: u64,
+    bit_flip: f64,
+    byte_drop: f64,
+    byte_duplication: f64,
+    truncation: f64,
+    pending: VecDeque<u8>,
+    kept: Option<u64>,
+    skipping: bool,
+}
+
+impl<R: Read> NoisyReader<R> {
+    /// Return a reader over `source` using `seed` for its random generator, without any
+    /// fault until rates are set
+    pub fn new(source: R, seed: u64) -> NoisyReader<R> {
+        NoisyReader {
+            source,
+            // xorshift generators never leave the zero state
+            state: seed.max(1),
+            bit_flip: 0.0,
+            byte_drop: 0.0,
+            byte_duplication: 0.0,
+            truncation: 0.0,
+            pending: VecDeque::new(),
+            kept: None,
+            skipping: false,
+        }
+    }
+
+    /// Flip one bit of a byte with probability `rate`
+    pub fn bit_flip(mut self, rate: f64) -> NoisyReader<R> {
+        self.bit_flip = rate;
+        self
+    }
+
+    /// Drop a byte with probability `rate`
+    pub fn byte_drop(mut self, rate: f64) -> NoisyReader<R> {
+        self.byte_drop = rate;
+        self
+    }
+
+    /// Send a byte twice with probability `rate`
+    pub fn byte_duplication(mut self, rate: f64) -> NoisyReader<R> {
+        self.byte_duplication = rate;
+        self
+    }
+
+    /// Truncate a frame with probability `rate`
+    pub fn truncation(mut self, rate: f64) -> NoisyReader<R> {
+        self.truncation = rate;
+        self
+    }
+
+    /// Return the source of the reader
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    fn next_random(&mut self) -> u64 {
+        // xorshift64*
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn chance(&mut self, rate: f64) -> bool {
+        rate > 0.0 && ((self.next_random() >> 11) as f64 / (1u64 << 53) as f64) < rate
+    }
+
+    fn push(&mut self, byte: u8) {
+        if byte == STX {
+            self.skipping = false;
+            self.kept = match self.chance(self.truncation) {
+                true => Some(self.next_random() % 256),
+                false => None,
+            };
+        }
+        if self.skipping {
+            return;
+        }
+        match self.kept {
+            Some(0) => {
+                self.kept = None;
+                self.skipping = true;
+                return;
+            }
+            Some(kept) => self.kept = Some(kept - 1),
+            None => (),
+        }
+        if self.chance(self.byte_drop) {
+            return;
+        }
+        let byte = match self.chance(self.bit_flip) {
+            true => byte ^ (1 << (self.next_random() % 8)),
+            false => byte,
+        };
+        self.pending.push_back(byte);
+        if self.chance(self.byte_duplication) {
+            self.pending.push_back(byte);
+        }
+    }
+}
+
+impl<R: Read> Read for NoisyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut data = vec![0; buf.len()];
+        while self.pending.is_empty() {
+            let count = self.source.read(&mut data)?;
+            if count == 0 {
+                return Ok(0);
+            }
+            for &byte in data[..count].iter() {
+                self.push(byte);
+            }
+        }
+        let count = buf.len().min(self.pending.len());
+        for (dest, byte) in buf.iter_mut().zip(self.pending.drain(..count)) {
+            *dest = byte;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::noise::NoisyReader;
+    use crate::simulator::fixture::frames;
+    use crate::{read_message, TeleinfoDecoder, TeleinfoParser, TeleinfoReader, Validation};
+    use std::io::Read;
+    fn captures() -> Vec<Vec<u8>> {
+        vec![
+            include_bytes!("../assets/stream_legacy_raw.txt").to_vec(),
+            include_bytes!("../assets/stream_standard_raw.txt").to_vec(),
+        ]
+    }
+    fn noisy(data: &[u8], seed: u64, rate: f64) -> NoisyReader<&[u8]> {
+        NoisyReader::new(data, seed)
+            .bit_flip(rate)
+            .byte_drop(rate)
+            .byte_duplication(rate)
+            .truncation(rate * 10.0)
+    }
+    fn read_all<R: Read>(mut reader: R) -> Vec<u8> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        data
+    }
+    #[test]
+    fn test_faults() {
+        let data = include_bytes!("../assets/stream_standard_raw.txt");
+        assert_eq!(read_all(NoisyReader::new(&data[..], 1)), &data[..]);
+        assert_eq!(
+            read_all(noisy(data, 1, 0.01)),
+            read_all(noisy(data, 1, 0.01))
+        );
+        assert_ne!(
+            read_all(noisy(data, 1, 0.01)),
+            read_all(noisy(data, 2, 0.01))
+        );
+        let dropped = read_all(NoisyReader::new(&data[..], 1).byte_drop(0.5));
+        assert!(dropped.len() > data.len() / 3 && dropped.len() < data.len() * 2 / 3);
+        let duplicated = read_all(NoisyReader::new(&data[..], 1).byte_duplication(1.0));
+        assert_eq!(duplicated.len(), data.len() * 2);
+        let flipped = read_all(NoisyReader::new(&data[..], 1).bit_flip(1.0));
+        assert!(flipped
+            .iter()
+            .zip(data.iter())
+            .all(|(a, b)| (a ^ b).count_ones() == 1));
+        // truncated frames lose their end, ETX included
+        let truncated = read_all(NoisyReader::new(&data[..], 1).truncation(1.0));
+        assert!(truncated.len() < data.len());
+        assert!(TeleinfoDecoder::new()
+            .feed(&truncated)
+            .iter()
+            .all(|m| m.as_ref().map_or(true, |m| !m.is_valid())));
+    }
+    #[test]
+    fn test_no_panic() {
+        let parsers = [
+            TeleinfoParser::new(),
+            TeleinfoParser::new().validation(Validation::Strict),
+            TeleinfoParser::new().validation(Validation::Lenient),
+        ];
+        for data in captures().iter() {
+            for seed in 1..40 {
+                for rate in [0.001, 0.01, 0.1].iter() {
+                    let corrupted = read_all(noisy(data, seed, *rate));
+                    for parser in parsers.iter() {
+                        let decoder = TeleinfoDecoder::new().parser(parser.clone()).capacity(512);
+                        let reader = TeleinfoReader::new(&corrupted[..]).decoder(decoder);
+                        for message in reader.flatten() {
+                            // valid messages hold only datasets with a valid checksum
+                            if message.is_valid() {
+                                assert!(message.get_invalid_labels().is_empty());
+                            }
+                        }
+                    }
+                    let mut source = &corrupted[..];
+                    let mut leftover = String::new();
+                    let mut parser = TeleinfoParser::new();
+                    // errors other than the end of the data leave the rest in leftover
+                    while !matches!(
+                        read_message(&mut source, &mut leftover, &mut parser),
+                        Err(crate::TeleinfoError::Io { .. })
+                    ) {}
+                }
+            }
+        }
+    }
+    #[test]
+    fn test_resync() {
+        for mode in [crate::TeleinfoMode::Legacy, crate::TeleinfoMode::Standard].iter() {
+            let clean = frames(*mode, 5);
+            let expected: Vec<_> = TeleinfoDecoder::new()
+                .feed(&clean)
+                .into_iter()
+                .map(|m| m.unwrap())
+                .collect();
+            assert_eq!(expected.len(), 5);
+            for seed in 1..40 {
+                for rate in [0.01, 0.1].iter() {
+                    let corrupted = read_all(noisy(&frames(*mode, 10), seed, *rate));
+                    let messages: Vec<_> = TeleinfoReader::new((&corrupted[..]).chain(&clean[..]))
+                        .filter_map(|m| m.ok())
+                        .collect();
+                    // no clean frame is lost, even after a truncated corrupted one
+                    assert!(messages.len() >= 5);
+                    let tail = &messages[messages.len() - 5..];
+                    assert!(tail == &expected[..], "seed {} rate {}", seed, rate);
+                }
+            }
+        }
+    }
+}
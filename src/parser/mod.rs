@@ -1,23 +1,49 @@
 use nom::{
     branch::alt, bytes::complete::tag, bytes::complete::tag_no_case, bytes::complete::take,
     bytes::complete::take_until, bytes::streaming::take_until as stream_take_until,
-    character::complete::anychar, character::complete::char, character::complete::digit1,
-    combinator::recognize, combinator::verify, multi::many1, sequence::delimited, sequence::tuple,
-    IResult,
+    character::complete::anychar, character::complete::char, combinator::map_opt,
+    combinator::recognize, combinator::verify, multi::many1, sequence::tuple, IResult,
 };
 
 use crate::parser::tags::*;
-use crate::{TeleinfoDate, TeleinfoMode, TeleinfoTuple};
-use chrono::{Local, TimeZone};
+use crate::{TeleinfoDate, TeleinfoError, TeleinfoMode, TeleinfoTuple};
 
 mod tags;
 
+pub use crate::parser::tags::{label_kind, LabelKind};
+
 fn get_beginning(input: &str) -> IResult<&str, &str> {
     recognize(tuple((stream_take_until("\u{02}"), tag("\u{02}"))))(input)
 }
 
+/// Frame is the content of a message between its STX and ETX characters, or the partial
+/// content of a message the meter aborted with an EOT character to send an urgent one
+#[derive(Debug, PartialEq)]
+pub enum Frame<'a> {
+    Complete(&'a str),
+    Interrupted(&'a str),
+}
+
+pub fn get_frame(input: &str) -> IResult<&str, Frame<'_>> {
+    let (rest, _) = get_beginning(input)?;
+    match rest.find(['\u{03}', '\u{04}']) {
+        Some(end) if rest[end..].starts_with('\u{04}') => {
+            Ok((&rest[end + 1..], Frame::Interrupted(&rest[..end])))
+        }
+        Some(end) => Ok((&rest[end + 1..], Frame::Complete(&rest[..end]))),
+        None => Err(nom::Err::Incomplete(nom::Needed::Unknown)),
+    }
+}
+
+/// Return the content of the first message of `input`, an interrupted message is an error
 pub fn get_message(input: &str) -> IResult<&str, &str> {
-    delimited(get_beginning, stream_take_until("\u{03}"), tag("\u{03}"))(input)
+    match get_frame(input)? {
+        (rest, Frame::Complete(content)) => Ok((rest, content)),
+        (_, Frame::Interrupted(_)) => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Tag,
+        ))),
+    }
 }
 
 fn separator(mode: TeleinfoMode) -> char {
@@ -39,11 +65,11 @@ fn parser_value_helper(input: &str, mode: TeleinfoMode) -> IResult<&str, &str> {
     take_until(&(separator(mode).to_string()) as &str)(input)
 }
 
-fn parser_dataset_legacy(input: &str) -> IResult<&str, TeleinfoTuple> {
+fn parser_dataset_legacy(input: &str) -> IResult<&str, TeleinfoTuple<'_>> {
     let mode = TeleinfoMode::Legacy;
     let (input, (_, tag, _, data, _, checksum, _)) = tuple((
         char('\u{0a}'),
-        parser_tag_legacy,
+        parser_label,
         char(separator(mode)),
         parser_value_legacy,
         char(separator(mode)),
@@ -53,17 +79,17 @@ fn parser_dataset_legacy(input: &str) -> IResult<&str, TeleinfoTuple> {
     Ok((input, (tag, data, checksum, None)))
 }
 
-fn parser_dataset_standard(input: &str) -> IResult<&str, TeleinfoTuple> {
+fn parser_dataset_standard(input: &str) -> IResult<&str, TeleinfoTuple<'_>> {
     alt((
         parser_dataset_standard_nohd,
         parser_dataset_standard_horodate,
     ))(input)
 }
-fn parser_dataset_standard_nohd(input: &str) -> IResult<&str, TeleinfoTuple> {
+fn parser_dataset_standard_nohd(input: &str) -> IResult<&str, TeleinfoTuple<'_>> {
     let mode = TeleinfoMode::Standard;
     let (input, (_, tag, _, data, _, checksum, _)) = tuple((
         char('\u{0a}'),
-        parser_tag_standard,
+        parser_label,
         char(separator(mode)),
         parser_value_standard,
         char(separator(mode)),
@@ -73,11 +99,11 @@ fn parser_dataset_standard_nohd(input: &str) -> IResult<&str, TeleinfoTuple> {
     Ok((input, (tag, data, checksum, None)))
 }
 
-fn parser_dataset_standard_horodate(input: &str) -> IResult<&str, TeleinfoTuple> {
+fn parser_dataset_standard_horodate(input: &str) -> IResult<&str, TeleinfoTuple<'_>> {
     let mode = TeleinfoMode::Standard;
     let (input, (_, tag, _, date, _, data, _, checksum, _)) = tuple((
         char('\u{0a}'),
-        parser_tag_standard_horodate,
+        parser_label,
         char(separator(mode)),
         parser_horodate,
         char(separator(mode)),
@@ -93,91 +119,207 @@ fn parser_horodate_season(input: &str) -> IResult<&str, &str> {
     alt((tag_no_case("h"), tag_no_case("e"), tag(" ")))(input)
 }
 
-fn parser_date_verifier(input: &str) -> IResult<&str, &str> {
-    digit1(input)
-}
-
 fn parser_horodate_date(input: &str) -> IResult<&str, &str> {
     verify(take(12usize), |s: &str| {
-        parser_date_verifier(s).unwrap() == ("" as &str, s)
+        s.chars().all(|c| c.is_ascii_digit())
     })(input)
 }
 
 fn parser_horodate(input: &str) -> IResult<&str, TeleinfoDate> {
-    match tuple((parser_horodate_season, parser_horodate_date))(input) {
-        Err(e) => Err(e),
-        Ok((r, (season, date))) => {
-            let raw_value = format!("{}{}", season, date);
-            Ok((
-                r,
-                TeleinfoDate {
-                    season: season.chars().next().unwrap(),
-                    date: Local.datetime_from_str(date, "%y%m%d%H%M%S").unwrap(),
-                    raw_value,
-                },
-            ))
-        }
-    }
+    map_opt(
+        recognize(tuple((parser_horodate_season, parser_horodate_date))),
+        TeleinfoDate::parse,
+    )(input)
 }
 
-pub fn parser_message(input: &str) -> IResult<&str, (Vec<TeleinfoTuple>, TeleinfoMode)> {
+pub fn parser_message(input: &str) -> IResult<&str, (Vec<TeleinfoTuple<'_>>, TeleinfoMode)> {
     alt((parser_message_legacy, parser_message_standard))(input)
 }
 
-pub fn parser_message_legacy(input: &str) -> IResult<&str, (Vec<TeleinfoTuple>, TeleinfoMode)> {
+pub fn parser_message_legacy(input: &str) -> IResult<&str, (Vec<TeleinfoTuple<'_>>, TeleinfoMode)> {
     match many1(parser_dataset_legacy)(input) {
         Ok((r, v)) => Ok((r, (v, TeleinfoMode::Legacy))),
         Err(e) => Err(e),
     }
 }
 
-pub fn parser_message_standard(input: &str) -> IResult<&str, (Vec<TeleinfoTuple>, TeleinfoMode)> {
+pub fn parser_message_standard(
+    input: &str,
+) -> IResult<&str, (Vec<TeleinfoTuple<'_>>, TeleinfoMode)> {
     match many1(parser_dataset_standard)(input) {
         Ok((r, v)) => Ok((r, (v, TeleinfoMode::Standard))),
         Err(e) => Err(e),
     }
 }
 
-pub fn validate_message(mode: TeleinfoMode, message: Vec<TeleinfoTuple>) -> bool {
-    message.clone().iter().map(|m| validate(mode, m)).all(|x| x)
+/// Parse a single dataset of a message in `mode`
+pub fn parser_dataset(mode: TeleinfoMode, input: &str) -> IResult<&str, TeleinfoTuple<'_>> {
+    match mode {
+        TeleinfoMode::Legacy => parser_dataset_legacy(input),
+        TeleinfoMode::Standard => parser_dataset_standard(input),
+    }
 }
 
-fn validate(mode: TeleinfoMode, values: &TeleinfoTuple) -> bool {
-    let (tag, value, cs, hd) = values.clone();
-    let include_sep = if let TeleinfoMode::Legacy = mode {
-        "".to_string()
-    } else {
-        separator(mode).to_string()
-    };
-    match hd {
-        None => {
-            calculate_checksum(&format!(
-                "{}{}{}{}",
-                tag,
-                separator(mode),
-                value,
-                include_sep
-            )) == cs
+/// Build the error for the dataset at the beginning of `rest` which could not be parsed.
+/// `message` is the message `rest` is a suffix of and `mode` the mode of the datasets
+/// already parsed, if any
+pub fn line_error(message: &str, rest: &str, mode: Option<TeleinfoMode>) -> TeleinfoError {
+    let offset = message.len() - rest.len();
+    let line = rest
+        .trim_start_matches('\n')
+        .split(['\r', '\n'])
+        .next()
+        .unwrap_or("");
+    let dataset = format!("\n{}\r", line);
+    if let Some(expected) = mode {
+        let other = match expected {
+            TeleinfoMode::Legacy => TeleinfoMode::Standard,
+            TeleinfoMode::Standard => TeleinfoMode::Legacy,
+        };
+        if parser_dataset(other, &dataset).is_ok() {
+            return TeleinfoError::ModeMismatch {
+                offset,
+                line: line.to_string(),
+                expected,
+            };
         }
-        Some(date) => {
-            calculate_checksum(&format!(
-                "{}{}{}{}{}{}",
-                tag,
-                separator(mode),
-                date.raw_value,
-                separator(mode),
-                value,
-                separator(mode)
-            )) == cs
+    }
+    let fields: Vec<&str> = line.split(separator(TeleinfoMode::Standard)).collect();
+    if fields.len() == 4 && fields[1].len() == 13 && parser_horodate(fields[1]).is_err() {
+        return TeleinfoError::InvalidHorodate {
+            offset,
+            line: line.to_string(),
+        };
+    }
+    TeleinfoError::Framing {
+        offset,
+        line: line.to_string(),
+    }
+}
+
+/// ChecksumMethod describes which characters of a dataset are summed for its checksum
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ChecksumMethod {
+    /// sum from the label to the last separator excluded, used by legacy meters
+    WithoutSeparator,
+    /// sum from the label to the last separator included, used by standard meters
+    WithSeparator,
+}
+
+impl ChecksumMethod {
+    /// Return the method defined by the specification for `mode`
+    pub fn default_for(mode: TeleinfoMode) -> ChecksumMethod {
+        match mode {
+            TeleinfoMode::Legacy => ChecksumMethod::WithoutSeparator,
+            TeleinfoMode::Standard => ChecksumMethod::WithSeparator,
+        }
+    }
+
+    /// Return the other method
+    pub fn other(self) -> ChecksumMethod {
+        match self {
+            ChecksumMethod::WithoutSeparator => ChecksumMethod::WithSeparator,
+            ChecksumMethod::WithSeparator => ChecksumMethod::WithoutSeparator,
         }
     }
 }
 
+pub fn validate_message(mode: TeleinfoMode, message: Vec<TeleinfoTuple<'_>>) -> bool {
+    message.iter().all(|m| validate(mode, m))
+}
+
+pub fn validate(mode: TeleinfoMode, values: &TeleinfoTuple) -> bool {
+    validate_with(ChecksumMethod::default_for(mode), mode, values)
+}
+
+pub fn validate_with(method: ChecksumMethod, mode: TeleinfoMode, values: &TeleinfoTuple) -> bool {
+    expected_checksum(method, mode, values) == values.2
+}
+
+/// Return the checksum the meter should have sent for the dataset `values` with `method`
+pub fn expected_checksum(
+    method: ChecksumMethod,
+    mode: TeleinfoMode,
+    values: &TeleinfoTuple,
+) -> char {
+    let (tag, value, _, hd) = values;
+    let last_sep = match method {
+        ChecksumMethod::WithoutSeparator => "".to_string(),
+        ChecksumMethod::WithSeparator => separator(mode).to_string(),
+    };
+    match hd {
+        None => calculate_checksum(&format!("{}{}{}{}", tag, separator(mode), value, last_sep)),
+        Some(date) => calculate_checksum(&format!(
+            "{}{}{}{}{}{}",
+            tag,
+            separator(mode),
+            date.raw_value,
+            separator(mode),
+            value,
+            last_sep
+        )),
+    }
+}
+
+/// Return the dataset `values` in wire format, from its line feed to its carriage return,
+/// with the checksum calculated with `method` in place of the one of `values`
+pub fn format_dataset(
+    method: ChecksumMethod,
+    mode: TeleinfoMode,
+    values: &TeleinfoTuple,
+) -> String {
+    let (tag, value, _, hd) = values;
+    format_fields(
+        method,
+        mode,
+        tag,
+        hd.as_ref().map(|date| date.raw_value.as_str()),
+        value,
+    )
+}
+
+/// Return the dataset made of `tag`, the raw `horodate` if any and `value` in wire format
+pub(crate) fn format_fields(
+    method: ChecksumMethod,
+    mode: TeleinfoMode,
+    tag: &str,
+    horodate: Option<&str>,
+    value: &str,
+) -> String {
+    let sep = separator(mode);
+    let content = match horodate {
+        None => format!("{}{}{}", tag, sep, value),
+        Some(date) => format!("{}{}{}{}{}", tag, sep, date, sep, value),
+    };
+    let checksum = match method {
+        ChecksumMethod::WithoutSeparator => calculate_checksum(&content),
+        ChecksumMethod::WithSeparator => calculate_checksum(&format!("{}{}", content, sep)),
+    };
+    format!("\n{}{}{}\r", content, sep, checksum)
+}
+
+/// Split the content of a message into its datasets, each starting with its line feed,
+/// along with their byte offset in `input`
+pub fn split_datasets(input: &str) -> Vec<(usize, &str)> {
+    if input.is_empty() {
+        return vec![];
+    }
+    let mut starts: Vec<usize> = input.match_indices('\n').map(|(i, _)| i).collect();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain(std::iter::once(&input.len())))
+        .map(|(&start, &end)| (start, &input[start..end]))
+        .collect()
+}
+
 fn calculate_checksum(input: &str) -> char {
     ((input
         .to_string()
         .chars()
-        .fold(0 as u32, |acc, c| acc + (c as u32))
+        .fold(0u32, |acc, c| acc + (c as u32))
         & 0x3F) as u8
         + 0x20) as char
 }
@@ -185,14 +327,19 @@ fn calculate_checksum(input: &str) -> char {
 #[cfg(test)]
 mod tests {
     use crate::parser::get_message;
+    use crate::parser::line_error;
     use crate::parser::parser_dataset_legacy;
     use crate::parser::parser_dataset_standard;
     use crate::parser::parser_horodate;
+    use crate::parser::parser_label;
     use crate::parser::parser_message;
-    use crate::parser::parser_tag_standard;
+    use crate::parser::split_datasets;
     use crate::parser::validate;
-    use crate::{TeleinfoDate, TeleinfoMode};
-    use chrono::{Local, TimeZone};
+    use crate::parser::validate_with;
+    use crate::parser::ChecksumMethod;
+    use crate::parser::{get_frame, Frame};
+    use crate::{Season, TeleinfoDate, TeleinfoError, TeleinfoMode};
+    use chrono::TimeZone;
     #[test]
     fn test_line() {
         let line_1 = "\u{0a}BBRHCJB 001478389 E\u{0d}";
@@ -211,7 +358,10 @@ mod tests {
                     'K',
                     Some(TeleinfoDate {
                         season: 'H',
-                        date: Local.ymd(2020, 2, 13).and_hms(8, 51, 18),
+                        date: Season::Winter
+                            .offset()
+                            .with_ymd_and_hms(2020, 2, 13, 8, 51, 18)
+                            .unwrap(),
                         raw_value: "H200213085118".to_string()
                     })
                 )
@@ -222,6 +372,17 @@ mod tests {
             parser_dataset_standard(line_std_nohd),
             Ok(("", ("EASF06", "000706363", '@', None)))
         );
+
+        let line_iinst1 = "\u{0a}IINST1 001 I\u{0d}";
+        assert_eq!(
+            parser_dataset_legacy(line_iinst1),
+            Ok(("", ("IINST1", "001", 'I', None)))
+        );
+        let line_unknown = "\u{0a}NEWLABEL\tSOMETHING NEW\tX\u{0d}";
+        assert_eq!(
+            parser_dataset_standard(line_unknown),
+            Ok(("", ("NEWLABEL", "SOMETHING NEW", 'X', None)))
+        );
     }
     #[test]
     fn test_parser_message() {
@@ -255,7 +416,7 @@ mod tests {
         let expect = vec![
              ("ADSC", "041776199277", 'I', None),
              ("VTIC", "02", 'J', None),
-             ("DATE", "", ';', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,14).and_hms(23,08,04), raw_value: "H200214230804".to_string() })),
+             ("DATE", "", ';', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,14, 23,8,4).unwrap(), raw_value: "H200214230804".to_string() })),
              ("NGTF", "     TEMPO      ", 'F', None),
              ("LTARF", "   HC  BLANC    ", '6', None),
              ("EAST", "021849106", '.', None),
@@ -285,22 +446,22 @@ mod tests {
              ("SINSTS1", "00664", 'G', None),
              ("SINSTS2", "01373", 'F', None),
              ("SINSTS3", "00664", 'I', None),
-             ("SMAXSN", "10802", '7', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,14).and_hms(17,51,35), raw_value: "H200214175135".to_string() })),
-             ("SMAXSN1", "03411", '&', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,14).and_hms(17,51,35), raw_value: "H200214175135".to_string() })),
-             ("SMAXSN2", "03899", ';', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,14).and_hms(17,51,35), raw_value: "H200214175135".to_string() })),
-             ("SMAXSN3", "03512", '*', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,14).and_hms(17,51,35), raw_value: "H200214175135".to_string() })),
-             ("SMAXSN-1", "09562", ' ', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,13).and_hms(08,51,18), raw_value: "H200213085118".to_string() })),
-             ("SMAXSN1-1", "03129", 'J', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,13).and_hms(08,51,18), raw_value: "H200213085118".to_string() })),
-             ("SMAXSN2-1", "03366", '@', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,13).and_hms(10,11,42), raw_value: "H200213101142".to_string() })),
-             ("SMAXSN3-1", "03191", 'K', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,13).and_hms(08,51,18), raw_value: "H200213085118".to_string() })),
-             ("CCASN", "01650", '5', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,14).and_hms(23,00,00), raw_value: "H200214230000".to_string() })),
-             ("CCASN-1", "00786", ' ', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,14).and_hms(22,50,00), raw_value: "H200214225000".to_string() })),
-             ("UMOY1", "237", '(', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,14).and_hms(23,00,00), raw_value: "H200214230000".to_string() })),
-             ("UMOY2", "238", '*', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,14).and_hms(23,00,00), raw_value: "H200214230000".to_string() })),
-             ("UMOY3", "236", ')', Some(TeleinfoDate { season: 'H', date: Local.ymd(2020,02,14).and_hms(23,00,00), raw_value: "H200214230000".to_string() })),
+             ("SMAXSN", "10802", '7', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,14, 17,51,35).unwrap(), raw_value: "H200214175135".to_string() })),
+             ("SMAXSN1", "03411", '&', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,14, 17,51,35).unwrap(), raw_value: "H200214175135".to_string() })),
+             ("SMAXSN2", "03899", ';', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,14, 17,51,35).unwrap(), raw_value: "H200214175135".to_string() })),
+             ("SMAXSN3", "03512", '*', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,14, 17,51,35).unwrap(), raw_value: "H200214175135".to_string() })),
+             ("SMAXSN-1", "09562", ' ', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,13, 8,51,18).unwrap(), raw_value: "H200213085118".to_string() })),
+             ("SMAXSN1-1", "03129", 'J', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,13, 8,51,18).unwrap(), raw_value: "H200213085118".to_string() })),
+             ("SMAXSN2-1", "03366", '@', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,13, 10,11,42).unwrap(), raw_value: "H200213101142".to_string() })),
+             ("SMAXSN3-1", "03191", 'K', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,13, 8,51,18).unwrap(), raw_value: "H200213085118".to_string() })),
+             ("CCASN", "01650", '5', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,14, 23,0,0).unwrap(), raw_value: "H200214230000".to_string() })),
+             ("CCASN-1", "00786", ' ', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,14, 22,50,0).unwrap(), raw_value: "H200214225000".to_string() })),
+             ("UMOY1", "237", '(', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,14, 23,0,0).unwrap(), raw_value: "H200214230000".to_string() })),
+             ("UMOY2", "238", '*', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,14, 23,0,0).unwrap(), raw_value: "H200214230000".to_string() })),
+             ("UMOY3", "236", ')', Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020,2,14, 23,0,0).unwrap(), raw_value: "H200214230000".to_string() })),
              ("STGE", "463A0800", 'K', None),
-             ("DPM1", "00", '\\', Some(TeleinfoDate { season: ' ', date: Local.ymd(2020,02,14).and_hms(06,00,00), raw_value: " 200214060000".to_string() })),
-             ("FPM1", "00", '_', Some(TeleinfoDate { season: ' ', date: Local.ymd(2020,02,15).and_hms(06,00,00), raw_value: " 200215060000".to_string() })),
+             ("DPM1", "00", '\\', Some(TeleinfoDate { season: ' ', date: Season::Winter.offset().with_ymd_and_hms(2020,2,14, 6,0,0).unwrap(), raw_value: " 200214060000".to_string() })),
+             ("FPM1", "00", '_', Some(TeleinfoDate { season: ' ', date: Season::Winter.offset().with_ymd_and_hms(2020,2,15, 6,0,0).unwrap(), raw_value: " 200215060000".to_string() })),
              ("MSG1", "PAS DE          MESSAGE         ", '<', None),
              ("PRM", "07361794479930", 'F', None),
              ("RELAIS", "001", 'C', None),
@@ -316,27 +477,37 @@ mod tests {
     }
     #[test]
     fn test_cs() {
-        assert_eq!(
-            validate(TeleinfoMode::Legacy, &("BBRHCJB", "001478389", 'E', None)),
-            true
-        );
-        assert_eq!(
-            validate(TeleinfoMode::Legacy, &("BBRHCJB", "001478389", 'F', None)),
-            false
-        );
-        assert_eq!(
-            validate(TeleinfoMode::Standard, &("EASF01", "004855593", 'I', None),),
-            true
-        );
-        assert_eq!(
-            validate(TeleinfoMode::Standard, &("EASF01", "004855593", 'J', None),),
-            false
-        );
+        assert!(validate(
+            TeleinfoMode::Legacy,
+            &("BBRHCJB", "001478389", 'E', None)
+        ));
+        assert!(!validate(
+            TeleinfoMode::Legacy,
+            &("BBRHCJB", "001478389", 'F', None)
+        ));
+        assert!(validate(
+            TeleinfoMode::Standard,
+            &("EASF01", "004855593", 'I', None)
+        ));
+        assert!(!validate(
+            TeleinfoMode::Standard,
+            &("EASF01", "004855593", 'J', None)
+        ));
+        assert!(validate_with(
+            ChecksumMethod::WithSeparator,
+            TeleinfoMode::Legacy,
+            &("BBRHCJB", "001478389", '%', None)
+        ));
+        assert!(validate_with(
+            ChecksumMethod::WithoutSeparator,
+            TeleinfoMode::Standard,
+            &("EASF01", "004855593", '@', None)
+        ));
     }
     #[test]
     fn test_standard_dataset() {
         assert_eq!(
-            parser_tag_standard("SINSTS1\t00664\tG\r"),
+            parser_label("SINSTS1\t00664\tG\r"),
             Ok(("\t00664\tG\r", "SINSTS1"))
         );
         assert_eq!(
@@ -348,7 +519,10 @@ mod tests {
     fn test_horodate() {
         let expected = TeleinfoDate {
             season: 'H',
-            date: Local.ymd(2008, 12, 25).and_hms(22, 35, 18),
+            date: Season::Winter
+                .offset()
+                .with_ymd_and_hms(2008, 12, 25, 22, 35, 18)
+                .unwrap(),
             raw_value: "H081225223518".to_string(),
         };
         let expected2 = expected.clone();
@@ -382,7 +556,7 @@ mod tests {
         let expect_standard = vec![
             ("ADSC","041776199277",'I',None),
             ("VTIC","02",'J',None),
-            ("DATE","",';',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(23, 8, 4), raw_value: "H200214230804".to_string() })),
+            ("DATE","",';',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 23, 8, 4).unwrap(), raw_value: "H200214230804".to_string() })),
             ("NGTF","     TEMPO      ",'F',None),
             ("LTARF","   HC  BLANC    ",'6',None),
             ("EAST","021849106",'.',None),
@@ -412,22 +586,22 @@ mod tests {
             ("SINSTS1","00664",'G',None),
             ("SINSTS2","01373",'F',None),
             ("SINSTS3","00664",'I',None),
-            ("SMAXSN","10802",'7',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(17, 51, 35), raw_value: "H200214175135".to_string() })),
-            ("SMAXSN1","03411",'&',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(17, 51, 35), raw_value: "H200214175135".to_string() })),
-            ("SMAXSN2","03899",';',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(17, 51, 35), raw_value: "H200214175135".to_string() })),
-            ("SMAXSN3","03512",'*',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(17, 51, 35), raw_value: "H200214175135".to_string() })),
-            ("SMAXSN-1","09562",' ',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 13).and_hms(8, 51, 18), raw_value: "H200213085118".to_string() })),
-            ("SMAXSN1-1","03129",'J',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 13).and_hms(8, 51, 18), raw_value: "H200213085118".to_string() })),
-            ("SMAXSN2-1","03366",'@',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 13).and_hms(10, 11, 42), raw_value: "H200213101142".to_string() })),
-            ("SMAXSN3-1","03191",'K',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 13).and_hms(8, 51, 18), raw_value: "H200213085118".to_string() })), 
-            ("CCASN","01650",'5',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(23, 0, 0), raw_value: "H200214230000".to_string() })),
-            ("CCASN-1","00786",' ',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(22, 50, 0), raw_value: "H200214225000".to_string() })),
-            ("UMOY1","237",'(',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(23, 0, 0), raw_value: "H200214230000".to_string() })),
-            ("UMOY2","238",'*',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(23, 0, 0), raw_value: "H200214230000".to_string() })),
-            ("UMOY3","236",')',Some(TeleinfoDate { season: 'H', date: Local.ymd(2020, 2, 14).and_hms(23, 0, 0), raw_value: "H200214230000".to_string() })),
+            ("SMAXSN","10802",'7',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 17, 51, 35).unwrap(), raw_value: "H200214175135".to_string() })),
+            ("SMAXSN1","03411",'&',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 17, 51, 35).unwrap(), raw_value: "H200214175135".to_string() })),
+            ("SMAXSN2","03899",';',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 17, 51, 35).unwrap(), raw_value: "H200214175135".to_string() })),
+            ("SMAXSN3","03512",'*',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 17, 51, 35).unwrap(), raw_value: "H200214175135".to_string() })),
+            ("SMAXSN-1","09562",' ',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 13, 8, 51, 18).unwrap(), raw_value: "H200213085118".to_string() })),
+            ("SMAXSN1-1","03129",'J',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 13, 8, 51, 18).unwrap(), raw_value: "H200213085118".to_string() })),
+            ("SMAXSN2-1","03366",'@',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 13, 10, 11, 42).unwrap(), raw_value: "H200213101142".to_string() })),
+            ("SMAXSN3-1","03191",'K',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 13, 8, 51, 18).unwrap(), raw_value: "H200213085118".to_string() })), 
+            ("CCASN","01650",'5',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 23, 0, 0).unwrap(), raw_value: "H200214230000".to_string() })),
+            ("CCASN-1","00786",' ',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 22, 50, 0).unwrap(), raw_value: "H200214225000".to_string() })),
+            ("UMOY1","237",'(',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 23, 0, 0).unwrap(), raw_value: "H200214230000".to_string() })),
+            ("UMOY2","238",'*',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 23, 0, 0).unwrap(), raw_value: "H200214230000".to_string() })),
+            ("UMOY3","236",')',Some(TeleinfoDate { season: 'H', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 23, 0, 0).unwrap(), raw_value: "H200214230000".to_string() })),
             ("STGE","463A0800",'K',None),
-            ("DPM1","00",'\\',Some(TeleinfoDate { season: ' ', date: Local.ymd(2020, 2, 14).and_hms(6, 0, 0), raw_value: " 200214060000".to_string() })),
-            ("FPM1","00",'_',Some(TeleinfoDate { season: ' ', date: Local.ymd(2020, 2, 15).and_hms(6, 0, 0), raw_value: " 200215060000".to_string() })),
+            ("DPM1","00",'\\',Some(TeleinfoDate { season: ' ', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 14, 6, 0, 0).unwrap(), raw_value: " 200214060000".to_string() })),
+            ("FPM1","00",'_',Some(TeleinfoDate { season: ' ', date: Season::Winter.offset().with_ymd_and_hms(2020, 2, 15, 6, 0, 0).unwrap(), raw_value: " 200215060000".to_string() })),
             ("MSG1","PAS DE          MESSAGE         ",'<',None),
             ("PRM","07361794479930",'F',None),
             ("RELAIS","001",'C',None),
@@ -464,4 +638,65 @@ mod tests {
             Err(nom::Err::Incomplete(nom::Needed::Unknown))
         )
     }
+    #[test]
+    fn test_get_frame() {
+        let data = "\u{2}\nADCO 031961098836 M\r\nOPT\u{4}\u{2}\nADCO 031961098836 M\r\u{3}";
+        let (rest, frame) = get_frame(data).unwrap();
+        assert_eq!(frame, Frame::Interrupted("\nADCO 031961098836 M\r\nOPT"));
+        assert!(get_message(data).is_err());
+        assert_eq!(
+            get_frame(rest),
+            Ok(("", Frame::Complete("\nADCO 031961098836 M\r")))
+        );
+        assert_eq!(
+            get_frame("\u{4}\u{2}\nADCO"),
+            Err(nom::Err::Incomplete(nom::Needed::Unknown))
+        );
+    }
+    #[test]
+    fn test_line_error() {
+        let message = "\nADCO 031961098836 M\r\nEASF01\t004855593\tI\r";
+        match line_error(message, &message[21..], Some(TeleinfoMode::Legacy)) {
+            TeleinfoError::ModeMismatch {
+                offset,
+                line,
+                expected,
+            } => {
+                assert_eq!(offset, 21);
+                assert_eq!(line, "EASF01\t004855593\tI");
+                assert_eq!(expected, TeleinfoMode::Legacy);
+            }
+            e => panic!("unexpected error {:?}", e),
+        }
+        let message = "\nSMAXSN\tH201314175135\t10802\t7\r";
+        match line_error(message, message, None) {
+            TeleinfoError::InvalidHorodate { offset, line } => {
+                assert_eq!(offset, 0);
+                assert_eq!(line, "SMAXSN\tH201314175135\t10802\t7");
+            }
+            e => panic!("unexpected error {:?}", e),
+        }
+        assert!(parser_horodate("H20131417513").is_err());
+        let message = "\nADCO\r";
+        match line_error(message, message, None) {
+            TeleinfoError::Framing { offset, line } => {
+                assert_eq!(offset, 0);
+                assert_eq!(line, "ADCO");
+            }
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+    #[test]
+    fn test_split_datasets() {
+        assert_eq!(
+            split_datasets("\nADCO 031961098836 M\r\nIINST 0\n1 X\r"),
+            vec![
+                (0, "\nADCO 031961098836 M\r"),
+                (21, "\nIINST 0"),
+                (29, "\n1 X\r")
+            ]
+        );
+        assert_eq!(split_datasets("M\r\nPAPP"), vec![(0, "M\r"), (2, "\nPAPP")]);
+        assert_eq!(split_datasets(""), vec![]);
+    }
 }
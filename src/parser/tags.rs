@@ -1,163 +1,85 @@
-use nom::{branch::alt, bytes::complete::tag, IResult};
+use nom::{bytes::complete::take_while1, IResult};
 
-fn parser_tag_legacy_1(input: &str) -> IResult<&str, &str> {
-    alt((
-        tag("ADCO"),
-        tag("OPTARIF"),
-        tag("ISOUSC"),
-        tag("PEJP"),
-        tag("PTEC"),
-        tag("DEMAIN"),
-        tag("IINST"),
-        tag("IINST1"),
-        tag("IINST2"),
-        tag("IINST3"),
-        tag("ADPS"),
-        tag("IMAX"),
-        tag("IMAX1"),
-        tag("IMAX2"),
-        tag("IMAX3"),
-        tag("PAPP"),
-        tag("PMAX"),
-        tag("HHPHC"),
-        tag("MOTDETAT"),
-        tag("PPOT"),
-    ))(input)
-}
-
-fn parser_tag_legacy_2(input: &str) -> IResult<&str, &str> {
-    alt((
-        tag("BASE"),
-        tag("HCHC"),
-        tag("HCHP"),
-        tag("EJPHN"),
-        tag("EJPHPM"),
-        tag("BBRHCJB"),
-        tag("BBRHPJB"),
-        tag("BBRHCJW"),
-        tag("BBRHPJW"),
-        tag("BBRHCJR"),
-        tag("BBRHPJR"),
-    ))(input)
-}
-
-fn parser_tag_legacy_3(input: &str) -> IResult<&str, &str> {
-    alt((tag("ADIR1"), tag("ADIR2"), tag("ADIR3")))(input)
-}
+use crate::TeleinfoMode;
 
-pub fn parser_tag_legacy(input: &str) -> IResult<&str, &str> {
-    alt((
-        parser_tag_legacy_1,
-        parser_tag_legacy_2,
-        parser_tag_legacy_3,
-    ))(input)
+/// LabelKind classifies a label against the labels defined in the Enedis specification
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LabelKind {
+    /// label is defined for the mode and has no horodate
+    Known,
+    /// label is defined for the mode and carries a horodate
+    Horodated,
+    /// label is not defined for the mode
+    Unknown,
 }
 
-fn parser_tag_standard_1(input: &str) -> IResult<&str, &str> {
-    alt((
-        tag("ADSC"),
-        tag("VTIC"),
-        tag("NGTF"),
-        tag("LTARF"),
-        tag("EAST"),
-        tag("EASF01"),
-        tag("EASF02"),
-        tag("EASF03"),
-        tag("EASF04"),
-        tag("EASF05"),
-        tag("EASF06"),
-        tag("EASF07"),
-        tag("EASF08"),
-        tag("EASF09"),
-        tag("EASF10"),
-        tag("EASD01"),
-        tag("EASD02"),
-        tag("EASD03"),
-        tag("EASD04"),
-        tag("EAIT"),
-    ))(input)
-}
+const LEGACY_LABELS: &[&str] = &[
+    "ADCO", "OPTARIF", "ISOUSC", "BASE", "HCHC", "HCHP", "EJPHN", "EJPHPM", "BBRHCJB", "BBRHPJB",
+    "BBRHCJW", "BBRHPJW", "BBRHCJR", "BBRHPJR", "PEJP", "PTEC", "DEMAIN", "IINST", "IINST1",
+    "IINST2", "IINST3", "ADPS", "IMAX", "IMAX1", "IMAX2", "IMAX3", "PAPP", "PMAX", "HHPHC",
+    "MOTDETAT", "PPOT", "ADIR1", "ADIR2", "ADIR3", "GAZ", "AUTRE",
+];
 
-fn parser_tag_standard_2(input: &str) -> IResult<&str, &str> {
-    alt((
-        tag("ADSC"),
-        tag("ERQ1"),
-        tag("ERQ2"),
-        tag("ERQ3"),
-        tag("ERQ4"),
-        tag("IRMS1"),
-        tag("IRMS2"),
-        tag("IRMS3"),
-        tag("URMS1"),
-        tag("URMS2"),
-        tag("URMS3"),
-        tag("PREF"),
-        tag("PCOUP"),
-        tag("SINSTS1"),
-        tag("SINSTS2"),
-        tag("SINSTS3"),
-        tag("SINSTS"),
-        tag("SINSTI"),
-        tag("STGE"),
-        tag("MSG1"),
-        tag("MSG2"),
-    ))(input)
-}
+const STANDARD_LABELS: &[&str] = &[
+    "ADSC", "VTIC", "NGTF", "LTARF", "EAST", "EASF01", "EASF02", "EASF03", "EASF04", "EASF05",
+    "EASF06", "EASF07", "EASF08", "EASF09", "EASF10", "EASD01", "EASD02", "EASD03", "EASD04",
+    "EAIT", "ERQ1", "ERQ2", "ERQ3", "ERQ4", "IRMS1", "IRMS2", "IRMS3", "URMS1", "URMS2", "URMS3",
+    "PREF", "PCOUP", "SINSTS", "SINSTS1", "SINSTS2", "SINSTS3", "SINSTI", "STGE", "MSG1", "MSG2",
+    "PRM", "RELAIS", "NTARF", "NJOURF", "NJOURF+1", "PJOURF+1", "PPOINTE",
+];
 
-fn parser_tag_standard_3(input: &str) -> IResult<&str, &str> {
-    alt((
-        tag("ADSC"),
-        tag("PRM"),
-        tag("RELAIS"),
-        tag("NTARF"),
-        tag("NJOURF+1"),
-        tag("NJOURF"),
-        tag("PJOURF+1"),
-        tag("PPOINTE"),
-    ))(input)
-}
-
-pub fn parser_tag_standard(input: &str) -> IResult<&str, &str> {
-    alt((
-        parser_tag_standard_1,
-        parser_tag_standard_2,
-        parser_tag_standard_3,
-    ))(input)
-}
+const STANDARD_HORODATED_LABELS: &[&str] = &[
+    "DATE",
+    "SMAXSN",
+    "SMAXSN1",
+    "SMAXSN2",
+    "SMAXSN3",
+    "SMAXSN-1",
+    "SMAXSN1-1",
+    "SMAXSN2-1",
+    "SMAXSN3-1",
+    "SMAXIN",
+    "SMAXIN-1",
+    "CCASN",
+    "CCASN-1",
+    "CCAIN",
+    "CCAIN-1",
+    "UMOY1",
+    "UMOY2",
+    "UMOY3",
+    "DPM1",
+    "FPM1",
+    "DPM2",
+    "FPM2",
+    "DPM3",
+    "FPM3",
+];
 
-fn parser_tag_standard_horodate_1(input: &str) -> IResult<&str, &str> {
-    alt((
-        tag("DATE"),
-        tag("SMAXSN1-1"),
-        tag("SMAXSN2-1"),
-        tag("SMAXSN3-1"),
-        tag("SMAXSN-1"),
-        tag("SMAXSN1"),
-        tag("SMAXSN2"),
-        tag("SMAXSN3"),
-        tag("SMAXSN"),
-        tag("SMAXIN-1"),
-        tag("SMAXIN"),
-        tag("CCASN-1"),
-        tag("CCASN"),
-        tag("CCAIN-1"),
-        tag("CCAIN"),
-        tag("UMOY1"),
-        tag("UMOY2"),
-        tag("UMOY3"),
-        tag("DPM1"),
-        tag("FPM1"),
-        tag("DPM2"),
-    ))(input)
+fn is_label_char(c: char) -> bool {
+    c.is_ascii_uppercase() || c.is_ascii_digit() || c == '+' || c == '-'
 }
 
-fn parser_tag_standard_horodate_2(input: &str) -> IResult<&str, &str> {
-    alt((tag("FPM2"), tag("DPM3"), tag("FPM3")))(input)
+/// Parse a label made of upper case letters, digits, '+' and '-'
+pub fn parser_label(input: &str) -> IResult<&str, &str> {
+    take_while1(is_label_char)(input)
 }
 
-pub fn parser_tag_standard_horodate(input: &str) -> IResult<&str, &str> {
-    alt((
-        parser_tag_standard_horodate_1,
-        parser_tag_standard_horodate_2,
-    ))(input)
+/// Return the LabelKind of `label` for `mode`
+/// # Example
+/// ```
+/// use teleinfo_nom::parser::{label_kind, LabelKind};
+/// use teleinfo_nom::TeleinfoMode;
+/// assert_eq!(label_kind(TeleinfoMode::Legacy, "IINST1"), LabelKind::Known);
+/// assert_eq!(label_kind(TeleinfoMode::Standard, "SMAXSN-1"), LabelKind::Horodated);
+/// assert_eq!(label_kind(TeleinfoMode::Standard, "IINST1"), LabelKind::Unknown);
+/// ```
+pub fn label_kind(mode: TeleinfoMode, label: &str) -> LabelKind {
+    match mode {
+        TeleinfoMode::Legacy if LEGACY_LABELS.contains(&label) => LabelKind::Known,
+        TeleinfoMode::Standard if STANDARD_LABELS.contains(&label) => LabelKind::Known,
+        TeleinfoMode::Standard if STANDARD_HORODATED_LABELS.contains(&label) => {
+            LabelKind::Horodated
+        }
+        _ => LabelKind::Unknown,
+    }
 }
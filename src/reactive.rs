@@ -0,0 +1,243 @@
+//! Reactive energy analysis from the ERQ1 to ERQ4 quadrant indices of standard mode messages
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::TeleinfoMessage;
+
+#[derive(Clone, Debug)]
+struct Sample {
+    time: DateTime<Utc>,
+    reactive: [u64; 4],
+    consumed: u64,
+    injected: u64,
+}
+
+impl Sample {
+    fn from_message(message: &TeleinfoMessage) -> Option<Sample> {
+        let time = message
+            .get_value("DATE".to_string())?
+            .horodate
+            .as_ref()?
+            .get_utc();
+        let energy = |label: &str| message.get_energy(label.to_string()).and_then(|e| e.ok());
+        let mut reactive = [0; 4];
+        for (quadrant, value) in reactive.iter_mut().enumerate() {
+            *value = message
+                .get_reactive_energy(format!("ERQ{}", quadrant + 1))
+                .and_then(|e| e.ok())?;
+        }
+        Some(Sample {
+            time,
+            reactive,
+            consumed: energy("EAST")?,
+            injected: energy("EAIT").unwrap_or(0),
+        })
+    }
+}
+
+/// ReactiveReport is the energy exchanged between two messages
+/// * duration the time between the messages, from their DATE horodates
+/// * reactive_energy the reactive energy of quadrants 1 to 4 in varh
+/// * consumed_energy the active energy consumed in Wh
+/// * injected_energy the active energy injected in Wh
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReactiveReport {
+    pub duration: Duration,
+    pub reactive_energy: [u64; 4],
+    pub consumed_energy: u64,
+    pub injected_energy: u64,
+}
+
+impl ReactiveReport {
+    /// Return the average reactive power in var, positive when inductive (quadrants 1 and
+    /// 2) and negative when capacitive (quadrants 3 and 4)
+    pub fn average_reactive_power(&self) -> f64 {
+        let [q1, q2, q3, q4] = self.reactive_energy;
+        let varh = (q1 + q2) as f64 - (q3 + q4) as f64;
+        varh * 3600.0 / self.duration.num_seconds().max(1) as f64
+    }
+
+    /// Return the power factor (cos φ) of the consumption, from the consumed active energy
+    /// and the reactive energy of quadrants 1 and 4. None if no energy was consumed
+    pub fn power_factor(&self) -> Option<f64> {
+        let [q1, _, _, q4] = self.reactive_energy;
+        power_factor(self.consumed_energy, q1 as f64 - q4 as f64)
+    }
+
+    /// Return the power factor (cos φ) of the injection, from the injected active energy and
+    /// the reactive energy of quadrants 2 and 3. None if no energy was injected
+    pub fn injection_power_factor(&self) -> Option<f64> {
+        let [_, q2, q3, _] = self.reactive_energy;
+        power_factor(self.injected_energy, q2 as f64 - q3 as f64)
+    }
+}
+
+fn power_factor(active: u64, reactive: f64) -> Option<f64> {
+    let active = active as f64;
+    let apparent = (active * active + reactive * reactive).sqrt();
+    match apparent > 0.0 {
+        true => Some(active / apparent),
+        false => None,
+    }
+}
+
+/// ReactiveAnalyser follows the energy indices of successive standard mode messages and
+/// reports the energy exchanged over the interval from the first message to the last one.
+/// Messages without DATE, EAST or one of the ERQ1 to ERQ4 indices are ignored, as well as
+/// messages older than the last one added
+/// # Example
+/// ```
+/// let mut analyser = teleinfo_nom::ReactiveAnalyser::new();
+/// let first = teleinfo_nom::parse_frame(concat!(
+///     "\nDATE\tH200214230000\t\t/\r\nEAST\t000010000\tP\r\nERQ1\t000001000\t<\r",
+///     "\nERQ2\t000000000\t<\r\nERQ3\t000000000\t=\r\nERQ4\t000000000\t>\r"))
+///     .unwrap();
+/// let second = teleinfo_nom::parse_frame(concat!(
+///     "\nDATE\tH200214233000\t\t2\r\nEAST\t000010400\tT\r\nERQ1\t000001300\t?\r",
+///     "\nERQ2\t000000000\t<\r\nERQ3\t000000000\t=\r\nERQ4\t000000000\t>\r"))
+///     .unwrap();
+/// assert!(analyser.push(&first));
+/// assert!(analyser.push(&second));
+/// let report = analyser.report().unwrap();
+/// assert_eq!(report.reactive_energy, [300, 0, 0, 0]);
+/// assert_eq!(report.average_reactive_power(), 600.0);
+/// assert_eq!(report.power_factor(), Some(0.8));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ReactiveAnalyser {
+    first: Option<Sample>,
+    last: Option<Sample>,
+}
+
+impl ReactiveAnalyser {
+    /// Return an analyser without any message
+    pub fn new() -> ReactiveAnalyser {
+        ReactiveAnalyser::default()
+    }
+
+    /// Add `message` at the end of the interval, return whether it holds the needed indices.
+    /// A message dated before the last one added is out of order and ignored
+    pub fn push(&mut self, message: &TeleinfoMessage) -> bool {
+        match Sample::from_message(message) {
+            Some(sample) => {
+                if let Some(latest) = self.last.as_ref().or(self.first.as_ref()) {
+                    if sample.time < latest.time {
+                        return false;
+                    }
+                }
+                if self.first.is_none() {
+                    self.first = Some(sample);
+                } else {
+                    self.last = Some(sample);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Return the report over the interval, None before two messages were added. Indices
+    /// going backwards, as after a meter reset, count as no energy
+    pub fn report(&self) -> Option<ReactiveReport> {
+        let (first, last) = (self.first.as_ref()?, self.last.as_ref()?);
+        let mut reactive_energy = [0; 4];
+        for (i, energy) in reactive_energy.iter_mut().enumerate() {
+            *energy = last.reactive[i].saturating_sub(first.reactive[i]);
+        }
+        Some(ReactiveReport {
+            duration: last.time - first.time,
+            reactive_energy,
+            consumed_energy: last.consumed.saturating_sub(first.consumed),
+            injected_energy: last.injected.saturating_sub(first.injected),
+        })
+    }
+
+    /// Start a new interval at the last message added
+    pub fn reset(&mut self) {
+        if self.last.is_some() {
+            self.first = self.last.take();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{format_fields, ChecksumMethod};
+    use crate::reactive::*;
+    use crate::{parse_frame, TeleinfoMode};
+    fn message(date: &str, east: u64, eait: Option<u64>, erq: [u64; 4]) -> TeleinfoMessage {
+        let field = |tag: &str, horodate, value: String| {
+            format_fields(
+                ChecksumMethod::WithSeparator,
+                TeleinfoMode::Standard,
+                tag,
+                horodate,
+                &value,
+            )
+        };
+        let mut frame = field("DATE", Some(date), String::new());
+        frame.push_str(&field("EAST", None, format!("{:09}", east)));
+        if let Some(eait) = eait {
+            frame.push_str(&field("EAIT", None, format!("{:09}", eait)));
+        }
+        for (i, value) in erq.iter().enumerate() {
+            frame.push_str(&field(
+                &format!("ERQ{}", i + 1),
+                None,
+                format!("{:09}", value),
+            ));
+        }
+        parse_frame(&frame).unwrap()
+    }
+    #[test]
+    fn test_analyser() {
+        let mut analyser = ReactiveAnalyser::new();
+        assert!(!analyser.push(&parse_frame("\nADCO 031961098836 M\r").unwrap()));
+        assert!(analyser.push(&message(
+            "E200714120000",
+            1000,
+            Some(5000),
+            [10, 20, 30, 40]
+        )));
+        assert_eq!(analyser.report(), None);
+        assert!(analyser.push(&message(
+            "E200714130000",
+            1000,
+            Some(7000),
+            [10, 520, 30, 40]
+        )));
+        let report = analyser.report().unwrap();
+        assert_eq!(report.duration, Duration::hours(1));
+        assert_eq!(report.reactive_energy, [0, 500, 0, 0]);
+        assert_eq!(report.injected_energy, 2000);
+        assert_eq!(report.average_reactive_power(), 500.0);
+        assert_eq!(report.power_factor(), None);
+        let cos_phi = report.injection_power_factor().unwrap();
+        assert!((cos_phi - 2000.0 / 2000f64.hypot(500.0)).abs() < 1e-9);
+        // capacitive consumption over the next interval
+        analyser.reset();
+        assert!(analyser.push(&message(
+            "E200714133000",
+            1500,
+            Some(7000),
+            [10, 520, 30, 140]
+        )));
+        let report = analyser.report().unwrap();
+        assert_eq!(report.duration, Duration::minutes(30));
+        assert_eq!(report.average_reactive_power(), -200.0);
+        assert!((report.power_factor().unwrap() - 500.0 / 500f64.hypot(100.0)).abs() < 1e-9);
+    }
+    #[test]
+    fn test_out_of_order() {
+        let mut analyser = ReactiveAnalyser::new();
+        assert!(analyser.push(&message("E200714120000", 1000, None, [10, 0, 0, 0])));
+        assert!(!analyser.push(&message("E200714110000", 900, None, [5, 0, 0, 0])));
+        assert_eq!(analyser.report(), None);
+        assert!(analyser.push(&message("E200714130000", 1200, None, [20, 0, 0, 0])));
+        assert!(!analyser.push(&message("E200714123000", 1100, None, [15, 0, 0, 0])));
+        let report = analyser.report().unwrap();
+        assert_eq!(report.duration, Duration::hours(1));
+        assert_eq!(report.reactive_energy, [10, 0, 0, 0]);
+        assert_eq!(report.consumed_energy, 200);
+    }
+}
@@ -0,0 +1,181 @@
+//! Iterator over the messages of a readable source
+
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read};
+
+use crate::{TeleinfoDecoder, TeleinfoError, TeleinfoMessage};
+
+/// TeleinfoReader reads messages from `source` until its end.
+/// Read timeouts, common on serial ports, are retried
+/// # Example
+/// ```
+/// use std::fs::File;
+/// let stream = File::open("assets/stream_legacy_raw.txt").unwrap();
+/// for message in teleinfo_nom::TeleinfoReader::new(stream) {
+///     assert_eq!(message.unwrap().get_current_index().unwrap(), "BBRHPJB");
+/// }
+/// ```
+pub struct TeleinfoReader<R: Read> {
+    source: R,
+    results: Results,
+}
+
+impl<R: Read> TeleinfoReader<R> {
+    /// Return a reader over `source` with a default `TeleinfoDecoder`
+    pub fn new(source: R) -> TeleinfoReader<R> {
+        TeleinfoReader {
+            source,
+            results: Results::new(),
+        }
+    }
+
+    /// Use `decoder` to decode the messages
+    pub fn decoder(mut self, decoder: TeleinfoDecoder) -> TeleinfoReader<R> {
+        self.results.decoder = decoder;
+        self
+    }
+
+    /// Skip errors and messages which are not valid instead of returning them
+    pub fn skip_invalid(mut self, skip_invalid: bool) -> TeleinfoReader<R> {
+        self.results.skip_invalid = skip_invalid;
+        self
+    }
+
+    /// Return the decoder used for the messages
+    pub fn get_decoder(&self) -> &TeleinfoDecoder {
+        &self.results.decoder
+    }
+
+    /// Return the source of the reader
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+impl<R: Read> Iterator for TeleinfoReader<R> {
+    type Item = Result<TeleinfoMessage, TeleinfoError>;
+
+    /// Return the next message, None at the end of the source
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = [0; 256];
+        loop {
+            if let Some(result) = self.results.pop() {
+                return Some(result);
+            }
+            if self.results.eof {
+                return None;
+            }
+            let read = self.source.read(&mut buf).map(|n| &buf[..n]);
+            if let Err(error) = self.results.push(read) {
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+/// Results decoded from the data read from a source, shared by `TeleinfoReader` and
+/// `TeleinfoStream`
+#[derive(Debug)]
+pub(crate) struct Results {
+    pub(crate) decoder: TeleinfoDecoder,
+    pub(crate) skip_invalid: bool,
+    pub(crate) eof: bool,
+    pending: VecDeque<Result<TeleinfoMessage, TeleinfoError>>,
+    read: usize,
+}
+
+impl Results {
+    pub(crate) fn new() -> Results {
+        Results {
+            decoder: TeleinfoDecoder::new(),
+            skip_invalid: false,
+            eof: false,
+            pending: VecDeque::new(),
+            read: 0,
+        }
+    }
+
+    /// Return the next result kept, None when more data is needed
+    pub(crate) fn pop(&mut self) -> Option<Result<TeleinfoMessage, TeleinfoError>> {
+        while let Some(result) = self.pending.pop_front() {
+            if self.keep(&result) {
+                return Some(result);
+            }
+        }
+        None
+    }
+
+    /// Decode the data of a read, an empty read being the end of the source. Read timeouts
+    /// and interruptions are ignored, other read errors are returned
+    pub(crate) fn push(&mut self, read: io::Result<&[u8]>) -> Result<(), TeleinfoError> {
+        match read {
+            Ok([]) => self.eof = true,
+            Ok(data) => {
+                self.read += data.len();
+                self.pending.extend(self.decoder.feed(data));
+            }
+            Err(ref e) if e.kind() == ErrorKind::TimedOut => (),
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e) => {
+                return Err(TeleinfoError::Io {
+                    offset: self.read,
+                    source: e,
+                })
+            }
+        }
+        Ok(())
+    }
+
+    fn keep(&self, result: &Result<TeleinfoMessage, TeleinfoError>) -> bool {
+        match result {
+            Ok(message) => !self.skip_invalid || message.is_valid(),
+            Err(_) => !self.skip_invalid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::TeleinfoReader;
+    use std::fs::File;
+    use std::io::Read;
+    #[test]
+    fn test_reader() {
+        let stream = File::open("assets/stream_standard_raw.txt").unwrap();
+        let messages: Vec<_> = TeleinfoReader::new(stream).collect();
+        assert_eq!(messages.len(), 7);
+        assert!(messages.iter().all(|m| m.is_ok()));
+        let stream = File::open("assets/stream_legacy_raw.txt").unwrap();
+        assert_eq!(TeleinfoReader::new(stream).count(), 1);
+    }
+    #[test]
+    fn test_skip_invalid() {
+        let data = "\u{2}\nADCO 031961098836 M\r\nPAPP 00120 #\r\u{3}\
+                    \u{2}\nADCO 031961098836 M\r\nPAPP 00120 $\r\u{3}";
+        let messages: Vec<_> = TeleinfoReader::new(data.as_bytes()).collect();
+        assert_eq!(messages.len(), 2);
+        assert!(!messages[0].as_ref().unwrap().is_valid());
+        let mut reader = TeleinfoReader::new(data.as_bytes()).skip_invalid(true);
+        assert!(reader.next().unwrap().unwrap().is_valid());
+        assert!(reader.next().is_none());
+        assert!(reader.next().is_none());
+    }
+    struct FailingSource(usize);
+    impl Read for FailingSource {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0 == 0 {
+                return Err(std::io::Error::other("unplugged"));
+            }
+            self.0 -= 1;
+            buf[0] = b'x';
+            Ok(1)
+        }
+    }
+    #[test]
+    fn test_io_error() {
+        match TeleinfoReader::new(FailingSource(3)).next() {
+            Some(Err(crate::TeleinfoError::Io { offset, .. })) => assert_eq!(offset, 3),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
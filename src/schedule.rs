@@ -0,0 +1,164 @@
+//! Decoding of the day schedules sent in standard mode, PJOURF+1 and PPOINTE
+
+use chrono::NaiveTime;
+
+/// RelayAction is the action on relay 1 (dry contact) at the start of a time slot
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RelayAction {
+    NoChange,
+    Temporised,
+    Open,
+    Closed,
+}
+
+/// TimeSlot is a switching point of a day schedule
+/// * start the time of day the slot starts at
+/// * index the supplier tariff index from the start of the slot, None if unchanged
+/// * relay the action on relay 1
+/// * action the raw 16 bits action code
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimeSlot {
+    pub start: NaiveTime,
+    pub index: Option<u8>,
+    pub relay: RelayAction,
+    pub action: u16,
+}
+
+/// DaySchedule is a day profile made of up to eleven time slots, ordered by start time
+#[derive(Clone, Debug, PartialEq)]
+pub struct DaySchedule {
+    pub slots: Vec<TimeSlot>,
+}
+
+impl DaySchedule {
+    /// Decode a day schedule from its representation as sent by the meter, HHMMSSSS blocks
+    /// with the start time and the hexadecimal action code of each slot, unused blocks being
+    /// NONUTILE. Return None if a block is malformed
+    /// # Example
+    /// ```
+    /// use chrono::NaiveTime;
+    /// let schedule = teleinfo_nom::DaySchedule::parse(
+    ///     "00004001 06004002 22004001 NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE",
+    /// ).unwrap();
+    /// assert_eq!(schedule.slots.len(), 3);
+    /// assert_eq!(schedule.slots[1].index, Some(2));
+    /// assert_eq!(schedule.index_at(NaiveTime::from_hms_opt(12, 0, 0).unwrap()), Some(2));
+    /// assert_eq!(schedule.index_at(NaiveTime::from_hms_opt(23, 0, 0).unwrap()), Some(1));
+    /// ```
+    pub fn parse(value: &str) -> Option<DaySchedule> {
+        let mut slots = Vec::new();
+        for block in value.split_whitespace() {
+            if block == "NONUTILE" {
+                continue;
+            }
+            // the start time is in decimal digits and the action in hexadecimal digits, signs
+            // accepted when parsing numbers are not
+            let bytes = block.as_bytes();
+            if bytes.len() != 8
+                || !bytes[..4].iter().all(u8::is_ascii_digit)
+                || !bytes[4..].iter().all(u8::is_ascii_hexdigit)
+            {
+                return None;
+            }
+            let hour = block[0..2].parse().ok()?;
+            let minute = block[2..4].parse().ok()?;
+            let action = u16::from_str_radix(&block[4..8], 16).ok()?;
+            slots.push(TimeSlot {
+                start: NaiveTime::from_hms_opt(hour, minute, 0)?,
+                index: Some((action & 0x0F) as u8).filter(|i| *i > 0),
+                relay: match action >> 14 {
+                    0 => RelayAction::NoChange,
+                    1 => RelayAction::Temporised,
+                    2 => RelayAction::Open,
+                    _ => RelayAction::Closed,
+                },
+                action,
+            });
+        }
+        slots.sort_by_key(|s| s.start);
+        Some(DaySchedule { slots })
+    }
+
+    /// Return the slot in progress at `time`, the last slot of the day before the first one
+    pub fn slot_at(&self, time: NaiveTime) -> Option<&TimeSlot> {
+        self.slots
+            .iter()
+            .rev()
+            .find(|s| s.start <= time)
+            .or_else(|| self.slots.last())
+    }
+
+    /// Return the supplier tariff index active at `time`, set by the slot in progress or by
+    /// the previous slots of the day. None if no slot sets an index
+    pub fn index_at(&self, time: NaiveTime) -> Option<u8> {
+        let started = self.slots.iter().filter(|s| s.start <= time);
+        let wrapped = self.slots.iter().filter(|s| s.start > time);
+        // slots after `time` set the index active at midnight
+        started.rev().chain(wrapped.rev()).find_map(|s| s.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schedule::*;
+    use crate::simulator::fixture::{at, decode};
+    use crate::{Contract, TeleinfoMode, TeleinfoSimulator};
+    use chrono::{Duration, Timelike};
+    #[test]
+    fn test_parse() {
+        let schedule = DaySchedule::parse("22008002 0000C001 NONUTILE 07300000").unwrap();
+        assert_eq!(
+            schedule
+                .slots
+                .iter()
+                .map(|s| s.start.hour())
+                .collect::<Vec<_>>(),
+            vec![0, 7, 22]
+        );
+        assert_eq!(schedule.slots[0].relay, RelayAction::Closed);
+        assert_eq!(schedule.slots[1].relay, RelayAction::NoChange);
+        assert_eq!(schedule.slots[1].index, None);
+        assert_eq!(schedule.slots[2].relay, RelayAction::Open);
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        // the slot at 7:30 keeps the index of the slot at 0:00
+        assert_eq!(schedule.index_at(at(8, 0)), Some(1));
+        assert_eq!(schedule.slot_at(at(8, 0)).unwrap().start, at(7, 30));
+        assert_eq!(schedule.index_at(at(23, 0)), Some(2));
+        assert_eq!(
+            DaySchedule::parse("NONUTILE").unwrap().index_at(at(1, 0)),
+            None
+        );
+        assert_eq!(DaySchedule::parse("2500400"), None);
+        assert_eq!(DaySchedule::parse("25004001"), None);
+        assert_eq!(DaySchedule::parse("+8004001"), None);
+        assert_eq!(DaySchedule::parse("08+54001"), None);
+        assert_eq!(DaySchedule::parse("0800+001"), None);
+    }
+    #[test]
+    fn test_simulator_schedule() {
+        let start = at(2020, 2, 14, 12);
+        let mut meter =
+            TeleinfoSimulator::new(TeleinfoMode::Standard, start).contract(Contract::OffPeak);
+        let message = decode(&meter);
+        let schedule = message.get_next_day_schedule().unwrap();
+        for hour in 0..24 {
+            let time = start
+                .date()
+                .succ_opt()
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap();
+            meter.advance(
+                (time - meter.get_time() + Duration::minutes(1))
+                    .to_std()
+                    .unwrap(),
+            );
+            assert_eq!(
+                schedule.index_at(time.time()),
+                Some(meter.get_supplier_index() as u8),
+                "{}",
+                time
+            );
+        }
+    }
+}
@@ -0,0 +1,799 @@
+//! Simulation of a meter producing a teleinfo stream
+
+use std::time::Duration;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+use crate::parser::{self, ChecksumMethod};
+use crate::{Season, TeleinfoMode, TempoColor};
+
+const STX: char = '\u{02}';
+const ETX: char = '\u{03}';
+
+/// Contract is the tariff option of a simulated meter
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Contract {
+    /// a single index
+    Base,
+    /// off-peak and peak hours (heures creuses)
+    OffPeak,
+    /// normal and mobile peak days (effacement jour de pointe)
+    Ejp,
+    /// blue, white and red days with off-peak and peak hours
+    Tempo,
+}
+
+/// Legacy label, legacy tariff period and standard tariff name of each supplier index
+type IndexNames = (&'static str, &'static str, &'static str);
+
+impl Contract {
+    fn indices(self) -> &'static [IndexNames] {
+        match self {
+            Contract::Base => &[("BASE", "TH..", "BASE")],
+            Contract::OffPeak => &[
+                ("HCHC", "HC..", "HEURE CREUSE"),
+                ("HCHP", "HP..", "HEURE PLEINE"),
+            ],
+            Contract::Ejp => &[
+                ("EJPHN", "HN..", "HEURE NORMALE"),
+                ("EJPHPM", "PM..", "HEURE POINTE"),
+            ],
+            Contract::Tempo => &[
+                ("BBRHCJB", "HCJB", "HC  BLEU"),
+                ("BBRHPJB", "HPJB", "HP  BLEU"),
+                ("BBRHCJW", "HCJW", "HC  BLANC"),
+                ("BBRHPJW", "HPJW", "HP  BLANC"),
+                ("BBRHCJR", "HCJR", "HC  ROUGE"),
+                ("BBRHPJR", "HPJR", "HP  ROUGE"),
+            ],
+        }
+    }
+
+    fn legacy_name(self) -> &'static str {
+        match self {
+            Contract::Base => "BASE",
+            Contract::OffPeak => "HC..",
+            Contract::Ejp => "EJP.",
+            Contract::Tempo => "BBR(",
+        }
+    }
+
+    fn standard_name(self) -> &'static str {
+        match self {
+            Contract::Base => "BASE",
+            Contract::OffPeak => "H PLEINE/CREUSE",
+            Contract::Ejp => "EJP",
+            Contract::Tempo => "TEMPO",
+        }
+    }
+}
+
+/// TariffCalendar gives the off-peak hours, the Tempo colors and the EJP peak days of a
+/// simulated meter. Tariff days start at 6:00, days missing from the calendar are blue
+/// normal days and EJP peak hours run from 7:00 to 1:00
+#[derive(Clone, Debug, PartialEq)]
+pub struct TariffCalendar {
+    off_peak: Vec<(NaiveTime, NaiveTime)>,
+    colors: Vec<(NaiveDate, TempoColor)>,
+    peak_days: Vec<NaiveDate>,
+}
+
+impl Default for TariffCalendar {
+    fn default() -> Self {
+        TariffCalendar::new()
+    }
+}
+
+impl TariffCalendar {
+    /// Return a calendar with off-peak hours from 22:00 to 6:00 and no special day
+    pub fn new() -> TariffCalendar {
+        TariffCalendar {
+            off_peak: vec![(hour(22), hour(6))],
+            colors: Vec::new(),
+            peak_days: Vec::new(),
+        }
+    }
+
+    /// Use `periods` as off-peak hours, each one from its start included to its end excluded
+    pub fn off_peak(mut self, periods: Vec<(NaiveTime, NaiveTime)>) -> TariffCalendar {
+        self.off_peak = periods;
+        self
+    }
+
+    /// Set the Tempo color of the tariff day `day`
+    pub fn color(mut self, day: NaiveDate, color: TempoColor) -> TariffCalendar {
+        self.colors.retain(|(d, _)| *d != day);
+        self.colors.push((day, color));
+        self
+    }
+
+    /// Make the tariff day `day` an EJP peak day
+    pub fn peak_day(mut self, day: NaiveDate) -> TariffCalendar {
+        self.peak_days.push(day);
+        self
+    }
+
+    /// Return whether `time` is in the off-peak hours
+    pub fn is_off_peak(&self, time: NaiveTime) -> bool {
+        self.off_peak.iter().any(|&(start, end)| {
+            if start <= end {
+                time >= start && time < end
+            } else {
+                time >= start || time < end
+            }
+        })
+    }
+
+    /// Return the Tempo color of the tariff day `day`
+    pub fn get_color(&self, day: NaiveDate) -> TempoColor {
+        self.colors
+            .iter()
+            .find(|(d, _)| *d == day)
+            .map_or(TempoColor::Blue, |(_, color)| *color)
+    }
+
+    /// Return whether the tariff day `day` is an EJP peak day
+    pub fn is_peak_day(&self, day: NaiveDate) -> bool {
+        self.peak_days.contains(&day)
+    }
+
+    fn is_peak_hour(&self, time: NaiveDateTime) -> bool {
+        self.is_peak_day(tariff_day(time)) && (time.time() >= hour(7) || time.time() < hour(1))
+    }
+
+    fn is_peak_notice(&self, time: NaiveDateTime) -> bool {
+        let now = time.time();
+        self.is_peak_day(tariff_day(time))
+            && now >= hour(6) + chrono::Duration::minutes(30)
+            && now < hour(7)
+    }
+}
+
+/// LoadProfile gives a power along a day in VA, interpolated between values evenly
+/// spread over the day, usually one per hour
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadProfile {
+    values: Vec<u32>,
+}
+
+impl Default for LoadProfile {
+    /// Return the profile of a household with morning and evening peaks
+    fn default() -> Self {
+        LoadProfile::new(vec![
+            300, 250, 250, 250, 250, 300, 800, 1500, 1200, 600, 500, 600, 1200, 900, 500, 500, 600,
+            900, 1800, 2500, 2200, 1500, 900, 500,
+        ])
+    }
+}
+
+impl LoadProfile {
+    /// Return a profile going through `values`, the first one at midnight
+    pub fn new(values: Vec<u32>) -> LoadProfile {
+        LoadProfile { values }
+    }
+
+    /// Return the profile of solar panels producing `peak` VA at 13:00
+    pub fn solar(peak: u32) -> LoadProfile {
+        LoadProfile::new(
+            (0..24)
+                .map(|h| match h {
+                    6..=20 => (peak as f64 * (std::f64::consts::PI * (h - 6) as f64 / 14.0).sin())
+                        .round() as u32,
+                    _ => 0,
+                })
+                .collect(),
+        )
+    }
+
+    /// Return the power of the profile at `time`
+    /// # Example
+    /// ```
+    /// use chrono::NaiveTime;
+    /// let profile = teleinfo_nom::LoadProfile::new(vec![1000, 3000]);
+    /// assert_eq!(profile.power_at(NaiveTime::from_hms_opt(6, 0, 0).unwrap()), 2000);
+    /// assert_eq!(profile.power_at(NaiveTime::from_hms_opt(18, 0, 0).unwrap()), 2000);
+    /// ```
+    pub fn power_at(&self, time: NaiveTime) -> u32 {
+        if self.values.is_empty() {
+            return 0;
+        }
+        let count = self.values.len();
+        let position = time.num_seconds_from_midnight() as f64 * count as f64 / 86400.0;
+        let index = position as usize % count;
+        let next = self.values[(index + 1) % count] as f64;
+        let ratio = position.fract();
+        (self.values[index] as f64 * (1.0 - ratio) + next * ratio).round() as u32
+    }
+}
+
+/// TeleinfoSimulator simulates a meter: its indices increase with the load profile
+/// according to its contract and tariff calendar, and `frame` returns the message it
+/// currently sends
+/// # Example
+/// ```
+/// use chrono::NaiveDate;
+/// use teleinfo_nom::{Contract, TeleinfoDecoder, TeleinfoMode, TeleinfoSimulator};
+/// let start = NaiveDate::from_ymd_opt(2020, 2, 14).unwrap().and_hms_opt(21, 0, 0).unwrap();
+/// let mut meter = TeleinfoSimulator::new(TeleinfoMode::Legacy, start).contract(Contract::OffPeak);
+/// meter.advance(std::time::Duration::from_secs(7200));
+/// let message = TeleinfoDecoder::new().feed(&meter.frame()).pop().unwrap().unwrap();
+/// assert!(message.is_valid());
+/// assert_eq!(message.get_current_index().unwrap(), "HCHC");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TeleinfoSimulator {
+    mode: TeleinfoMode,
+    contract: Contract,
+    three_phase: bool,
+    subscribed: u32,
+    load: LoadProfile,
+    production: Option<LoadProfile>,
+    calendar: TariffCalendar,
+    address: String,
+    time: NaiveDateTime,
+    supplier: [f64; 10],
+    distributor: [f64; 4],
+    injected: f64,
+    max_today: (u32, NaiveDateTime),
+    max_yesterday: (u32, NaiveDateTime),
+    max_injected_today: (u32, NaiveDateTime),
+    max_injected_yesterday: (u32, NaiveDateTime),
+}
+
+impl TeleinfoSimulator {
+    /// Return a single phase meter with a base contract of 9 kVA sending messages in `mode`,
+    /// with the default load profile and its clock set to `start`
+    pub fn new(mode: TeleinfoMode, start: NaiveDateTime) -> TeleinfoSimulator {
+        TeleinfoSimulator {
+            mode,
+            contract: Contract::Base,
+            three_phase: false,
+            subscribed: 9,
+            load: LoadProfile::default(),
+            production: None,
+            calendar: TariffCalendar::new(),
+            address: "031961098836".to_string(),
+            time: start,
+            supplier: [0.0; 10],
+            distributor: [0.0; 4],
+            injected: 0.0,
+            max_today: (0, start),
+            max_yesterday: (0, start),
+            max_injected_today: (0, start),
+            max_injected_yesterday: (0, start),
+        }
+    }
+
+    /// Use `contract` as tariff option
+    pub fn contract(mut self, contract: Contract) -> TeleinfoSimulator {
+        self.contract = contract;
+        self
+    }
+
+    /// Simulate a three-phase meter, the load being balanced on the phases
+    pub fn three_phase(mut self, three_phase: bool) -> TeleinfoSimulator {
+        self.three_phase = three_phase;
+        self
+    }
+
+    /// Use `subscribed` kVA as subscribed power
+    pub fn subscribed(mut self, subscribed: u32) -> TeleinfoSimulator {
+        self.subscribed = subscribed;
+        self
+    }
+
+    /// Use `load` as consumption profile
+    pub fn load(mut self, load: LoadProfile) -> TeleinfoSimulator {
+        self.load = load;
+        self
+    }
+
+    /// Simulate a producer with `production` as production profile, the surplus over the
+    /// consumption being injected
+    pub fn production(mut self, production: LoadProfile) -> TeleinfoSimulator {
+        self.production = Some(production);
+        self
+    }
+
+    /// Use `calendar` for the tariff periods
+    pub fn calendar(mut self, calendar: TariffCalendar) -> TeleinfoSimulator {
+        self.calendar = calendar;
+        self
+    }
+
+    /// Use `address` as meter address, 12 digits
+    pub fn address(mut self, address: String) -> TeleinfoSimulator {
+        self.address = address;
+        self
+    }
+
+    /// Start the supplier indices at `indices` Wh
+    pub fn indices(mut self, indices: Vec<u64>) -> TeleinfoSimulator {
+        for (index, value) in self.supplier.iter_mut().zip(indices) {
+            *index = value as f64;
+        }
+        self
+    }
+
+    /// Return the time of the meter clock
+    pub fn get_time(&self) -> NaiveDateTime {
+        self.time
+    }
+
+    /// Return the current supplier tariff index, from 1
+    pub fn get_supplier_index(&self) -> usize {
+        self.supplier_index_at(self.time)
+    }
+
+    /// Run the meter for `duration`, by steps of at most one minute
+    pub fn advance(&mut self, duration: Duration) {
+        let mut remaining = duration.as_millis() as i64;
+        while remaining > 0 {
+            let step = remaining.min(60_000);
+            let (consumed, injected) = self.powers();
+            let hours = step as f64 / 3_600_000.0;
+            self.supplier[self.get_supplier_index() - 1] += consumed as f64 * hours;
+            self.distributor[self.distributor_index() - 1] += consumed as f64 * hours;
+            self.injected += injected as f64 * hours;
+            let previous = self.time;
+            self.time += chrono::Duration::milliseconds(step);
+            if self.time.date() != previous.date() {
+                self.max_yesterday = self.max_today;
+                self.max_injected_yesterday = self.max_injected_today;
+                self.max_today = (0, self.time);
+                self.max_injected_today = (0, self.time);
+            }
+            let (consumed, injected) = self.powers();
+            if consumed > self.max_today.0 {
+                self.max_today = (consumed, self.time);
+            }
+            if injected > self.max_injected_today.0 {
+                self.max_injected_today = (injected, self.time);
+            }
+            remaining -= step;
+        }
+    }
+
+    /// Return the message currently sent by the meter, enclosed in STX and ETX characters
+    pub fn frame(&self) -> Vec<u8> {
+        let method = ChecksumMethod::default_for(self.mode);
+        let datasets = match self.mode {
+            TeleinfoMode::Legacy => self.legacy_datasets(),
+            TeleinfoMode::Standard => self.standard_datasets(),
+        };
+        let mut frame = STX.to_string();
+        for (label, horodate, value) in datasets {
+            frame.push_str(&parser::format_fields(
+                method,
+                self.mode,
+                &label,
+                horodate.as_deref(),
+                &value,
+            ));
+        }
+        frame.push(ETX);
+        frame.into_bytes()
+    }
+
+    fn powers(&self) -> (u32, u32) {
+        let now = self.time.time();
+        let consumed = self.load.power_at(now);
+        let produced = self.production.as_ref().map_or(0, |p| p.power_at(now));
+        if consumed >= produced {
+            (consumed - produced, 0)
+        } else {
+            (0, produced - consumed)
+        }
+    }
+
+    fn phases(&self) -> u32 {
+        if self.three_phase {
+            3
+        } else {
+            1
+        }
+    }
+
+    fn supplier_index_at(&self, time: NaiveDateTime) -> usize {
+        let off_peak = self.calendar.is_off_peak(time.time());
+        let period = if off_peak { 1 } else { 2 };
+        match self.contract {
+            Contract::Base => 1,
+            Contract::OffPeak => period,
+            Contract::Ejp if self.calendar.is_peak_hour(time) => 2,
+            Contract::Ejp => 1,
+            Contract::Tempo => match self.calendar.get_color(tariff_day(time)) {
+                TempoColor::Blue => period,
+                TempoColor::White => 2 + period,
+                TempoColor::Red => 4 + period,
+            },
+        }
+    }
+
+    fn distributor_index(&self) -> usize {
+        let off_peak =
+            self.contract != Contract::Base && self.calendar.is_off_peak(self.time.time());
+        let summer = (4..=10).contains(&self.time.month());
+        match (summer, off_peak) {
+            (false, true) => 1,
+            (false, false) => 2,
+            (true, true) => 3,
+            (true, false) => 4,
+        }
+    }
+
+    fn colors(&self) -> (TempoColor, TempoColor) {
+        let today = tariff_day(self.time);
+        let tomorrow = today.succ_opt().unwrap_or(today);
+        (
+            self.calendar.get_color(today),
+            self.calendar.get_color(tomorrow),
+        )
+    }
+
+    fn legacy_datasets(&self) -> Vec<(String, Option<String>, String)> {
+        let mut datasets = Vec::new();
+        let mut add = |label: &str, value: String| datasets.push((label.to_string(), None, value));
+        let (consumed, _) = self.powers();
+        let indices = self.contract.indices();
+        add("ADCO", self.address.clone());
+        add("OPTARIF", self.contract.legacy_name().to_string());
+        add(
+            "ISOUSC",
+            format!("{:02}", self.subscribed * 5 / self.phases()),
+        );
+        for (i, (label, _, _)) in indices.iter().enumerate() {
+            add(label, format!("{:09}", self.supplier[i] as u64));
+        }
+        add("PTEC", indices[self.get_supplier_index() - 1].1.to_string());
+        if self.contract == Contract::Ejp && self.calendar.is_peak_notice(self.time) {
+            add("PEJP", "30".to_string());
+        }
+        if self.contract == Contract::Tempo {
+            let demain = match self.colors().1 {
+                TempoColor::Blue => "BLEU",
+                TempoColor::White => "BLAN",
+                TempoColor::Red => "ROUG",
+            };
+            add("DEMAIN", demain.to_string());
+        }
+        let current = format!("{:03}", consumed / self.phases() / 230);
+        if self.three_phase {
+            add("IINST1", current.clone());
+            add("IINST2", current.clone());
+            add("IINST3", current);
+            add("IMAX1", "060".to_string());
+            add("IMAX2", "060".to_string());
+            add("IMAX3", "060".to_string());
+            add("PMAX", format!("{:05}", self.max_today.0));
+        } else {
+            add("IINST", current);
+            add("IMAX", "090".to_string());
+        }
+        add("PAPP", format!("{:05}", consumed));
+        add("HHPHC", "A".to_string());
+        add("MOTDETAT", "000000".to_string());
+        if self.three_phase {
+            add("PPOT", "00".to_string());
+        }
+        datasets
+    }
+
+    fn standard_datasets(&self) -> Vec<(String, Option<String>, String)> {
+        let mut datasets = Vec::new();
+        let mut add = |label: &str, horodate: Option<String>, value: String| {
+            datasets.push((label.to_string(), horodate, value))
+        };
+        let (consumed, injected) = self.powers();
+        let phases: Vec<String> = match self.three_phase {
+            true => (1..=3).map(|p| p.to_string()).collect(),
+            false => vec!["1".to_string()],
+        };
+        let per_phase = |power: u32| power / self.phases();
+        let index = self.get_supplier_index();
+        add("ADSC", None, self.address.clone());
+        add("VTIC", None, "02".to_string());
+        add("DATE", Some(horodate(self.time)), "".to_string());
+        add(
+            "NGTF",
+            None,
+            format!("{:^16}", self.contract.standard_name()),
+        );
+        add(
+            "LTARF",
+            None,
+            format!("{:^16}", self.contract.indices()[index - 1].2),
+        );
+        let total: f64 = self.supplier.iter().sum();
+        add("EAST", None, format!("{:09}", total as u64));
+        for (i, value) in self.supplier.iter().enumerate() {
+            add(
+                &format!("EASF{:02}", i + 1),
+                None,
+                format!("{:09}", *value as u64),
+            );
+        }
+        for (i, value) in self.distributor.iter().enumerate() {
+            add(
+                &format!("EASD{:02}", i + 1),
+                None,
+                format!("{:09}", *value as u64),
+            );
+        }
+        if self.production.is_some() {
+            add("EAIT", None, format!("{:09}", self.injected as u64));
+        }
+        for phase in phases.iter() {
+            let current = per_phase(consumed) / 230;
+            add(&format!("IRMS{}", phase), None, format!("{:03}", current));
+        }
+        for phase in phases.iter() {
+            add(&format!("URMS{}", phase), None, "230".to_string());
+        }
+        add("PREF", None, format!("{:02}", self.subscribed));
+        add("PCOUP", None, format!("{:02}", self.subscribed));
+        add("SINSTS", None, format!("{:05}", consumed));
+        if self.three_phase {
+            for phase in phases.iter() {
+                add(
+                    &format!("SINSTS{}", phase),
+                    None,
+                    format!("{:05}", per_phase(consumed)),
+                );
+            }
+        }
+        for (suffix, (max, time)) in [("", self.max_today), ("-1", self.max_yesterday)].iter() {
+            add(
+                &format!("SMAXSN{}", suffix),
+                Some(horodate(*time)),
+                format!("{:05}", max),
+            );
+            if self.three_phase {
+                for phase in phases.iter() {
+                    add(
+                        &format!("SMAXSN{}{}", phase, suffix),
+                        Some(horodate(*time)),
+                        format!("{:05}", per_phase(*max)),
+                    );
+                }
+            }
+        }
+        if self.production.is_some() {
+            add("SINSTI", None, format!("{:05}", injected));
+            let (max, time) = self.max_injected_today;
+            add("SMAXIN", Some(horodate(time)), format!("{:05}", max));
+            let (max, time) = self.max_injected_yesterday;
+            add("SMAXIN-1", Some(horodate(time)), format!("{:05}", max));
+        }
+        let average = self.time
+            - chrono::Duration::seconds(
+                self.time.minute() as i64 % 10 * 60 + self.time.second() as i64,
+            );
+        for phase in phases.iter() {
+            add(
+                &format!("UMOY{}", phase),
+                Some(horodate(average)),
+                "230".to_string(),
+            );
+        }
+        add("STGE", None, format!("{:08X}", self.status_register()));
+        add("MSG1", None, "PAS DE          MESSAGE         ".to_string());
+        add("PRM", None, format!("{:0>14}", self.address));
+        let relay = self.calendar.is_off_peak(self.time.time()) && self.contract != Contract::Base;
+        add("RELAIS", None, format!("{:03}", relay as u8));
+        add("NTARF", None, format!("{:02}", index));
+        add("NJOURF", None, "00".to_string());
+        add("NJOURF+1", None, "00".to_string());
+        add("PJOURF+1", None, self.next_day_schedule());
+        datasets
+    }
+
+    fn status_register(&self) -> u32 {
+        let tempo_bits = |color| match color {
+            TempoColor::Blue => 1,
+            TempoColor::White => 2,
+            TempoColor::Red => 3,
+        };
+        // standard mode, Euridis enabled and secured, CPL locked
+        let mut register: u32 = 1 << 17 | 3 << 19 | 1 << 21;
+        register |= (self.get_supplier_index() as u32 - 1) << 10;
+        register |= (self.distributor_index() as u32 - 1) << 14;
+        if self.production.is_some() {
+            register |= 1 << 8;
+            if self.powers().1 > 0 {
+                register |= 1 << 9;
+            }
+        }
+        match self.contract {
+            Contract::Tempo => {
+                let (today, tomorrow) = self.colors();
+                register |= tempo_bits(today) << 24 | tempo_bits(tomorrow) << 26;
+            }
+            Contract::Ejp if self.calendar.is_peak_notice(self.time) => register |= 1 << 28,
+            Contract::Ejp if self.calendar.is_peak_hour(self.time) => register |= 1 << 30,
+            _ => (),
+        }
+        register
+    }
+
+    fn next_day_schedule(&self) -> String {
+        let day = self
+            .time
+            .date()
+            .succ_opt()
+            .unwrap_or_else(|| self.time.date());
+        let mut times = vec![hour(0)];
+        match self.contract {
+            Contract::Base => (),
+            Contract::OffPeak | Contract::Tempo => {
+                for &(start, end) in self.calendar.off_peak.iter() {
+                    times.push(start);
+                    times.push(end);
+                }
+            }
+            Contract::Ejp => times.extend([hour(1), hour(7)].iter()),
+        }
+        times.sort();
+        times.dedup();
+        let mut slots: Vec<String> = Vec::new();
+        let mut last = 0;
+        for time in times {
+            let index = self.supplier_index_at(day.and_time(time));
+            if index != last {
+                slots.push(format!(
+                    "{:02}{:02}{:04X}",
+                    time.hour(),
+                    time.minute(),
+                    0x4000 | index
+                ));
+                last = index;
+            }
+        }
+        slots.resize(11, "NONUTILE".to_string());
+        slots.join(" ")
+    }
+}
+
+fn hour(hour: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, 0, 0).unwrap_or(NaiveTime::MIN)
+}
+
+fn tariff_day(time: NaiveDateTime) -> NaiveDate {
+    (time - chrono::Duration::hours(6)).date()
+}
+
+/// Return the horodate of `time` with its season in French legal time
+fn horodate(time: NaiveDateTime) -> String {
+    let season = match Season::of(time) {
+        Season::Summer => 'E',
+        Season::Winter => 'H',
+    };
+    format!("{}{}", season, time.format("%y%m%d%H%M%S"))
+}
+
+/// Simulated meters shared by the tests of the crate
+#[cfg(test)]
+pub(crate) mod fixture {
+    use std::time::Duration;
+
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use crate::{TeleinfoDecoder, TeleinfoMessage, TeleinfoMode, TeleinfoSimulator};
+
+    /// Return the time of the day `year`-`month`-`day` at `hour`
+    pub(crate) fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    /// Return the single message decoded from the current frame of `meter`
+    pub(crate) fn decode(meter: &TeleinfoSimulator) -> TeleinfoMessage {
+        let mut messages = TeleinfoDecoder::new().feed(&meter.frame());
+        assert_eq!(messages.len(), 1);
+        messages.pop().unwrap().unwrap()
+    }
+
+    /// Return `count` frames sent every 2 seconds by a meter in `mode` from 2020-02-14 21:00
+    pub(crate) fn frames(mode: TeleinfoMode, count: usize) -> Vec<u8> {
+        let mut meter = TeleinfoSimulator::new(mode, at(2020, 2, 14, 21));
+        let mut data = Vec::new();
+        for _ in 0..count {
+            data.extend(meter.frame());
+            meter.advance(Duration::from_secs(2));
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulator::fixture::{at, decode};
+    use crate::simulator::*;
+    use crate::TeleinfoMeterType;
+    fn start() -> NaiveDateTime {
+        at(2020, 2, 14, 21)
+    }
+    #[test]
+    fn test_frames() {
+        let contracts = [
+            Contract::Base,
+            Contract::OffPeak,
+            Contract::Ejp,
+            Contract::Tempo,
+        ];
+        for mode in [TeleinfoMode::Legacy, TeleinfoMode::Standard].iter() {
+            for contract in contracts.iter() {
+                for three_phase in [false, true].iter() {
+                    let mut meter = TeleinfoSimulator::new(*mode, start())
+                        .contract(*contract)
+                        .three_phase(*three_phase)
+                        .production(LoadProfile::solar(3000));
+                    meter.advance(Duration::from_secs(86400));
+                    let message = decode(&meter);
+                    assert!(message.is_valid());
+                    assert_eq!(message.mode, *mode);
+                    assert!(message.get_unknown_labels().is_empty());
+                    let meter_type = match three_phase {
+                        true => TeleinfoMeterType::TriPhase,
+                        false => TeleinfoMeterType::MonoPhase,
+                    };
+                    assert_eq!(message.get_meter_type(), meter_type);
+                    let indices = message.get_billing_indices().unwrap();
+                    let current = message.get_current_index().unwrap();
+                    assert!(indices.contains(&current));
+                }
+            }
+        }
+    }
+    #[test]
+    fn test_indices() {
+        let calendar = TariffCalendar::new()
+            .color(start().date(), TempoColor::Red)
+            .color(start().date().succ_opt().unwrap(), TempoColor::White);
+        let mut meter = TeleinfoSimulator::new(TeleinfoMode::Standard, start())
+            .contract(Contract::Tempo)
+            .calendar(calendar)
+            .load(LoadProfile::new(vec![3600]));
+        let message = decode(&meter);
+        assert_eq!(message.get_current_index().unwrap(), "EASF06");
+        let status = message.get_status().unwrap();
+        assert_eq!(status.tempo_today, Some(TempoColor::Red));
+        assert_eq!(status.tempo_tomorrow, Some(TempoColor::White));
+        assert_eq!(
+            message.get_value("PJOURF+1".to_string()).unwrap().value,
+            "00004005 06004004 22004003 NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE NONUTILE"
+        );
+        // 1 hour of red peak hours then 8 hours of red off-peak hours at 3600 VA
+        meter.advance(Duration::from_secs(9 * 3600));
+        let message = decode(&meter);
+        assert_eq!(message.get_current_index().unwrap(), "EASF04");
+        assert_eq!(
+            message.get_energy("EASF05".to_string()).unwrap().unwrap(),
+            28800
+        );
+        assert_eq!(
+            message.get_energy("EASF06".to_string()).unwrap().unwrap(),
+            3600
+        );
+        assert_eq!(
+            message.get_energy("EAST".to_string()).unwrap().unwrap(),
+            32400
+        );
+        assert!(message.get_value("EAIT".to_string()).is_none());
+    }
+    #[test]
+    fn test_horodate() {
+        let time = |m, d, h| {
+            NaiveDate::from_ymd_opt(2020, m, d)
+                .unwrap()
+                .and_hms_opt(h, 0, 0)
+                .unwrap()
+        };
+        assert_eq!(horodate(time(3, 29, 1)), "H200329010000");
+        assert_eq!(horodate(time(3, 29, 3)), "E200329030000");
+        assert_eq!(horodate(time(10, 25, 2)), "E201025020000");
+        assert_eq!(horodate(time(10, 25, 3)), "H201025030000");
+    }
+}
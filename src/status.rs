@@ -0,0 +1,406 @@
+//! Decoding of the STGE status and RELAIS registers sent in standard mode and of the PPOT and
+//! MOTDETAT registers sent in legacy mode
+
+use crate::TeleinfoMode;
+
+/// CutOffState describes the state of the meter cut-off device (organe de coupure)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CutOffState {
+    Closed,
+    OpenOverpower,
+    OpenOvervoltage,
+    OpenLoadShedding,
+    OpenRemoteOrder,
+    OpenOverheatHighCurrent,
+    OpenOverheatLowCurrent,
+    Reserved(u8),
+}
+
+/// EuridisStatus describes the state of the Euridis communication output
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EuridisStatus {
+    Disabled,
+    Enabled,
+    EnabledSecured,
+    Reserved(u8),
+}
+
+/// CplStatus describes the state of the CPL (power line) communication
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CplStatus {
+    NewUnlocked,
+    NewLocked,
+    Registered,
+    Reserved(u8),
+}
+
+/// TempoColor is the color of a day for a Tempo contract
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TempoColor {
+    Blue,
+    White,
+    Red,
+}
+
+/// MobilePeak identifies one of the three mobile peak periods (pointes mobiles)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MobilePeak {
+    Pm1,
+    Pm2,
+    Pm3,
+}
+
+/// TeleinfoStatus represents the STGE register of a standard mode message, one field
+/// per bit group as described in the Enedis specification
+#[derive(Clone, Debug, PartialEq)]
+pub struct TeleinfoStatus {
+    /// dry contact (contact sec) is open
+    pub dry_contact_open: bool,
+    /// state of the cut-off device
+    pub cut_off: CutOffState,
+    /// distributor terminal cover is open
+    pub cover_open: bool,
+    /// overvoltage on one of the phases
+    pub overvoltage: bool,
+    /// reference power is currently exceeded
+    pub reference_power_exceeded: bool,
+    /// meter works as a producer
+    pub producer: bool,
+    /// active energy is negative
+    pub negative_energy: bool,
+    /// current supplier tariff index, from 1 to 16
+    pub supplier_index: u8,
+    /// current distributor tariff index, from 1 to 4
+    pub distributor_index: u8,
+    /// meter clock is in degraded mode
+    pub clock_degraded: bool,
+    /// mode of the teleinfo output
+    pub tic_mode: TeleinfoMode,
+    /// state of the Euridis output
+    pub euridis: EuridisStatus,
+    /// state of the CPL communication
+    pub cpl: CplStatus,
+    /// CPL is synchronized
+    pub cpl_synchronized: bool,
+    /// Tempo color of the current day, None if not announced
+    pub tempo_today: Option<TempoColor>,
+    /// Tempo color of the next day, None if not announced
+    pub tempo_tomorrow: Option<TempoColor>,
+    /// mobile peak notice, None if no notice
+    pub mobile_peak_notice: Option<MobilePeak>,
+    /// mobile peak in progress, None if no mobile peak
+    pub mobile_peak: Option<MobilePeak>,
+}
+
+impl TeleinfoStatus {
+    /// Decode a STGE register from its integer value
+    /// # Example
+    /// ```
+    /// let status = teleinfo_nom::TeleinfoStatus::from_register(0x463A0800);
+    /// assert_eq!(status.supplier_index, 3);
+    /// assert_eq!(status.tempo_today, Some(teleinfo_nom::TempoColor::White));
+    /// ```
+    pub fn from_register(register: u32) -> TeleinfoStatus {
+        let bits = |shift: u32, width: u32| ((register >> shift) & ((1 << width) - 1)) as u8;
+        TeleinfoStatus {
+            dry_contact_open: bits(0, 1) == 1,
+            cut_off: match bits(1, 3) {
+                0 => CutOffState::Closed,
+                1 => CutOffState::OpenOverpower,
+                2 => CutOffState::OpenOvervoltage,
+                3 => CutOffState::OpenLoadShedding,
+                4 => CutOffState::OpenRemoteOrder,
+                5 => CutOffState::OpenOverheatHighCurrent,
+                6 => CutOffState::OpenOverheatLowCurrent,
+                x => CutOffState::Reserved(x),
+            },
+            cover_open: bits(4, 1) == 1,
+            overvoltage: bits(6, 1) == 1,
+            reference_power_exceeded: bits(7, 1) == 1,
+            producer: bits(8, 1) == 1,
+            negative_energy: bits(9, 1) == 1,
+            supplier_index: bits(10, 4) + 1,
+            distributor_index: bits(14, 2) + 1,
+            clock_degraded: bits(16, 1) == 1,
+            tic_mode: if bits(17, 1) == 1 {
+                TeleinfoMode::Standard
+            } else {
+                TeleinfoMode::Legacy
+            },
+            euridis: match bits(19, 2) {
+                0 => EuridisStatus::Disabled,
+                1 => EuridisStatus::Enabled,
+                3 => EuridisStatus::EnabledSecured,
+                x => EuridisStatus::Reserved(x),
+            },
+            cpl: match bits(21, 2) {
+                0 => CplStatus::NewUnlocked,
+                1 => CplStatus::NewLocked,
+                2 => CplStatus::Registered,
+                x => CplStatus::Reserved(x),
+            },
+            cpl_synchronized: bits(23, 1) == 1,
+            tempo_today: tempo_color(bits(24, 2)),
+            tempo_tomorrow: tempo_color(bits(26, 2)),
+            mobile_peak_notice: mobile_peak(bits(28, 2)),
+            mobile_peak: mobile_peak(bits(30, 2)),
+        }
+    }
+
+    /// Decode a STGE register from its hexadecimal representation as sent by the meter
+    /// # Example
+    /// ```
+    /// let status = teleinfo_nom::TeleinfoStatus::from_hex("463A0800").unwrap();
+    /// assert_eq!(status.tic_mode, teleinfo_nom::TeleinfoMode::Standard);
+    /// assert!(teleinfo_nom::TeleinfoStatus::from_hex("STGE").is_none());
+    /// ```
+    pub fn from_hex(value: &str) -> Option<TeleinfoStatus> {
+        u32::from_str_radix(value.trim(), 16)
+            .ok()
+            .map(TeleinfoStatus::from_register)
+    }
+}
+
+/// PhasePresence represents the PPOT register of legacy three-phase meters, whether the
+/// voltage of each phase is present
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PhasePresence {
+    /// voltage presence of phases 1 to 3
+    pub phases: [bool; 3],
+}
+
+impl PhasePresence {
+    /// Decode a PPOT register from its hexadecimal representation as sent by the meter,
+    /// bits 1 to 3 being set when the voltage of phases 1 to 3 is missing
+    /// # Example
+    /// ```
+    /// let presence = teleinfo_nom::PhasePresence::from_hex("04").unwrap();
+    /// assert_eq!(presence.phases, [true, false, true]);
+    /// assert_eq!(presence.get_lost_phases(), vec![2]);
+    /// ```
+    pub fn from_hex(value: &str) -> Option<PhasePresence> {
+        let register = u8::from_str_radix(value.trim(), 16).ok()?;
+        Some(PhasePresence {
+            phases: [1, 2, 3].map(|phase| register & (1 << phase) == 0),
+        })
+    }
+
+    /// Return the phases whose voltage is missing, from 1 to 3
+    pub fn get_lost_phases(&self) -> Vec<u8> {
+        (1..=3)
+            .filter(|phase| !self.phases[*phase as usize - 1])
+            .collect()
+    }
+
+    /// Return whether the voltage of the three phases is present
+    pub fn all_present(&self) -> bool {
+        self.phases.iter().all(|p| *p)
+    }
+}
+
+/// MeterState represents the MOTDETAT register of legacy meters. Its bits are reserved to the
+/// distributor, the specification only defines the nominal state 000000
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MeterState {
+    /// raw value of the register
+    pub register: u32,
+}
+
+impl MeterState {
+    /// Decode a MOTDETAT register from its hexadecimal representation as sent by the meter
+    /// # Example
+    /// ```
+    /// let state = teleinfo_nom::MeterState::from_hex("000000").unwrap();
+    /// assert!(state.is_nominal());
+    /// assert!(teleinfo_nom::MeterState::from_hex("MOTDETAT").is_none());
+    /// ```
+    pub fn from_hex(value: &str) -> Option<MeterState> {
+        u32::from_str_radix(value.trim(), 16)
+            .ok()
+            .map(|register| MeterState { register })
+    }
+
+    /// Return whether the meter reports no anomaly
+    pub fn is_nominal(&self) -> bool {
+        self.register == 0
+    }
+}
+
+/// RelayStates represents the RELAIS register of standard mode messages, whether each relay
+/// is closed: relay 1 is the dry contact, relays 2 to 8 are virtual
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RelayStates {
+    /// states of relays 1 to 8, true when closed
+    pub relays: [bool; 8],
+}
+
+/// RelayChange is the switch of a relay between two messages
+/// * relay the relay number, from 1 to 8
+/// * closed whether the relay is now closed
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RelayChange {
+    pub relay: u8,
+    pub closed: bool,
+}
+
+impl RelayStates {
+    /// Decode a RELAIS register from its integer value, bit 0 being relay 1
+    pub fn from_register(register: u8) -> RelayStates {
+        let mut relays = [false; 8];
+        for (bit, relay) in relays.iter_mut().enumerate() {
+            *relay = register & (1 << bit) != 0;
+        }
+        RelayStates { relays }
+    }
+
+    /// Decode a RELAIS register from its decimal representation as sent by the meter
+    /// # Example
+    /// ```
+    /// let states = teleinfo_nom::RelayStates::from_decimal("005").unwrap();
+    /// assert!(states.dry_contact());
+    /// assert_eq!(states.is_closed(3), Some(true));
+    /// assert_eq!(states.is_closed(2), Some(false));
+    /// assert_eq!(states.is_closed(9), None);
+    /// ```
+    pub fn from_decimal(value: &str) -> Option<RelayStates> {
+        value.trim().parse().ok().map(RelayStates::from_register)
+    }
+
+    /// Return whether relay 1, the dry contact, is closed
+    pub fn dry_contact(&self) -> bool {
+        self.relays[0]
+    }
+
+    /// Return whether `relay`, from 1 to 8, is closed, None for another relay number
+    pub fn is_closed(&self, relay: u8) -> Option<bool> {
+        relay
+            .checked_sub(1)
+            .and_then(|i| self.relays.get(i as usize))
+            .copied()
+    }
+
+    /// Return the relays switched since `previous`, by relay number
+    pub fn changes(&self, previous: &RelayStates) -> Vec<RelayChange> {
+        (1..=8)
+            .zip(self.relays.iter().zip(previous.relays.iter()))
+            .filter(|(_, (now, before))| now != before)
+            .map(|(relay, (closed, _))| RelayChange {
+                relay,
+                closed: *closed,
+            })
+            .collect()
+    }
+}
+
+fn tempo_color(value: u8) -> Option<TempoColor> {
+    match value {
+        1 => Some(TempoColor::Blue),
+        2 => Some(TempoColor::White),
+        3 => Some(TempoColor::Red),
+        _ => None,
+    }
+}
+
+fn mobile_peak(value: u8) -> Option<MobilePeak> {
+    match value {
+        1 => Some(MobilePeak::Pm1),
+        2 => Some(MobilePeak::Pm2),
+        3 => Some(MobilePeak::Pm3),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::status::*;
+    use crate::TeleinfoMode;
+    #[test]
+    fn test_from_register() {
+        assert_eq!(
+            TeleinfoStatus::from_hex("463A0800"),
+            Some(TeleinfoStatus {
+                dry_contact_open: false,
+                cut_off: CutOffState::Closed,
+                cover_open: false,
+                overvoltage: false,
+                reference_power_exceeded: false,
+                producer: false,
+                negative_energy: false,
+                supplier_index: 3,
+                distributor_index: 1,
+                clock_degraded: false,
+                tic_mode: TeleinfoMode::Standard,
+                euridis: EuridisStatus::EnabledSecured,
+                cpl: CplStatus::NewLocked,
+                cpl_synchronized: false,
+                tempo_today: Some(TempoColor::White),
+                tempo_tomorrow: Some(TempoColor::Blue),
+                mobile_peak_notice: None,
+                mobile_peak: Some(MobilePeak::Pm1),
+            })
+        );
+    }
+    #[test]
+    fn test_bit_groups() {
+        let status = TeleinfoStatus::from_register(0xFFFF_FFFF);
+        assert!(status.dry_contact_open);
+        assert_eq!(status.cut_off, CutOffState::Reserved(7));
+        assert!(status.cover_open);
+        assert!(status.overvoltage);
+        assert!(status.reference_power_exceeded);
+        assert!(status.producer);
+        assert!(status.negative_energy);
+        assert_eq!(status.supplier_index, 16);
+        assert_eq!(status.distributor_index, 4);
+        assert!(status.clock_degraded);
+        assert_eq!(status.tic_mode, TeleinfoMode::Standard);
+        assert_eq!(status.euridis, EuridisStatus::EnabledSecured);
+        assert_eq!(status.cpl, CplStatus::Reserved(3));
+        assert!(status.cpl_synchronized);
+        assert_eq!(status.tempo_today, Some(TempoColor::Red));
+        assert_eq!(status.tempo_tomorrow, Some(TempoColor::Red));
+        assert_eq!(status.mobile_peak_notice, Some(MobilePeak::Pm3));
+        assert_eq!(status.mobile_peak, Some(MobilePeak::Pm3));
+        assert_eq!(
+            TeleinfoStatus::from_register(0x0000_0004).cut_off,
+            CutOffState::OpenOvervoltage
+        );
+    }
+    #[test]
+    fn test_legacy_registers() {
+        let presence = PhasePresence::from_hex("00").unwrap();
+        assert!(presence.all_present());
+        assert!(presence.get_lost_phases().is_empty());
+        // bit 0 is not significant
+        assert_eq!(PhasePresence::from_hex("01"), Some(presence));
+        let presence = PhasePresence::from_hex("0E").unwrap();
+        assert_eq!(presence.phases, [false; 3]);
+        assert_eq!(presence.get_lost_phases(), vec![1, 2, 3]);
+        assert_eq!(PhasePresence::from_hex("PPOT"), None);
+        assert!(!MeterState::from_hex("000100").unwrap().is_nominal());
+    }
+    #[test]
+    fn test_relay_states() {
+        let off = RelayStates::from_decimal("000").unwrap();
+        assert_eq!(off.relays, [false; 8]);
+        let states = RelayStates::from_decimal("129").unwrap();
+        assert!(states.dry_contact());
+        assert_eq!(states.is_closed(8), Some(true));
+        assert_eq!(states.is_closed(0), None);
+        assert_eq!(
+            states.changes(&off),
+            vec![
+                RelayChange {
+                    relay: 1,
+                    closed: true
+                },
+                RelayChange {
+                    relay: 8,
+                    closed: true
+                }
+            ]
+        );
+        assert!(states.changes(&states).is_empty());
+        assert_eq!(RelayStates::from_decimal("256"), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! Asynchronous stream of the messages of an `AsyncRead` source, enabled by the `async` feature
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::reader::Results;
+use crate::{TeleinfoDecoder, TeleinfoError, TeleinfoMessage};
+
+/// TeleinfoStream yields the messages read from `source` until its end.
+/// Partial messages are kept in the stream between polls, so dropping a pending `next()`
+/// future does not lose data
+/// # Example
+/// ```
+/// use futures_util::StreamExt;
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let data: &[u8] = b"\x02\nADCO 031961098836 M\r\nPAPP 00120 $\r\x03";
+/// let mut stream = teleinfo_nom::TeleinfoStream::new(data);
+/// let message = stream.next().await.unwrap().unwrap();
+/// assert_eq!(message.get_value("PAPP".to_string()).unwrap().value, "00120");
+/// assert!(stream.next().await.is_none());
+/// # });
+/// ```
+pub struct TeleinfoStream<R: AsyncRead + Unpin> {
+    source: R,
+    results: Results,
+}
+
+impl<R: AsyncRead + Unpin> TeleinfoStream<R> {
+    /// Return a stream over `source` with a default `TeleinfoDecoder`
+    pub fn new(source: R) -> TeleinfoStream<R> {
+        TeleinfoStream {
+            source,
+            results: Results::new(),
+        }
+    }
+
+    /// Use `decoder` to decode the messages
+    pub fn decoder(mut self, decoder: TeleinfoDecoder) -> TeleinfoStream<R> {
+        self.results.decoder = decoder;
+        self
+    }
+
+    /// Skip errors and messages which are not valid instead of yielding them
+    pub fn skip_invalid(mut self, skip_invalid: bool) -> TeleinfoStream<R> {
+        self.results.skip_invalid = skip_invalid;
+        self
+    }
+
+    /// Return the decoder used for the messages
+    pub fn get_decoder(&self) -> &TeleinfoDecoder {
+        &self.results.decoder
+    }
+
+    /// Return the source of the stream
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for TeleinfoStream<R> {
+    type Item = Result<TeleinfoMessage, TeleinfoError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut buf = [0; 256];
+        loop {
+            if let Some(result) = this.results.pop() {
+                return Poll::Ready(Some(result));
+            }
+            if this.results.eof {
+                return Poll::Ready(None);
+            }
+            let mut read_buf = ReadBuf::new(&mut buf);
+            let read = match Pin::new(&mut this.source).poll_read(cx, &mut read_buf) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(read) => read.map(|()| read_buf.filled()),
+            };
+            if let Err(error) = this.results.push(read) {
+                return Poll::Ready(Some(Err(error)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stream::TeleinfoStream;
+    use futures_util::{FutureExt, StreamExt};
+    use tokio::io::AsyncWriteExt;
+    #[tokio::test]
+    async fn test_duplex() {
+        let (mut writer, reader) = tokio::io::duplex(64);
+        let mut stream = TeleinfoStream::new(reader);
+        let data = include_bytes!("../assets/stream_standard_raw.txt");
+        let feeder = tokio::spawn(async move {
+            for chunk in data.chunks(100) {
+                writer.write_all(chunk).await.unwrap();
+            }
+        });
+        let mut count = 0;
+        while let Some(message) = stream.next().await {
+            assert!(message.is_ok());
+            count += 1;
+        }
+        feeder.await.unwrap();
+        assert_eq!(count, 7);
+    }
+    #[tokio::test]
+    async fn test_cancel_safety() {
+        let (mut writer, reader) = tokio::io::duplex(64);
+        let mut stream = TeleinfoStream::new(reader);
+        writer
+            .write_all(b"\x02\nADCO 031961098836 M\r\nPA")
+            .await
+            .unwrap();
+        // a poll interrupted on a partial message keeps it
+        assert!(stream.next().now_or_never().is_none());
+        writer.write_all(b"PP 00120 $\r\x03").await.unwrap();
+        drop(writer);
+        let message = stream.next().await.unwrap().unwrap();
+        assert!(message.is_valid());
+        assert_eq!(
+            message.get_value("ADCO".to_string()).unwrap().value,
+            "031961098836"
+        );
+        assert!(stream.next().await.is_none());
+    }
+}
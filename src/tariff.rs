@@ -0,0 +1,217 @@
+//! Tariff options of the supplier contract, read from OPTARIF in legacy mode and from NGTF
+//! in standard mode
+
+use crate::TeleinfoError;
+
+/// TariffPeriod is a tariff period of a tariff option, counted by its own energy index
+/// * ptec the period as sent by PTEC in legacy mode
+/// * index the label of its energy index in legacy mode
+/// * name the name of the period, as sent by LTARF in standard mode without its padding
+/// * supplier the number of its supplier energy index in standard mode, 1 for EASF01
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TariffPeriod {
+    pub ptec: &'static str,
+    pub index: &'static str,
+    pub name: &'static str,
+    pub supplier: u8,
+}
+
+const fn period(
+    ptec: &'static str,
+    index: &'static str,
+    name: &'static str,
+    supplier: u8,
+) -> TariffPeriod {
+    TariffPeriod {
+        ptec,
+        index,
+        name,
+        supplier,
+    }
+}
+
+const BASE: &[TariffPeriod] = &[period("TH..", "BASE", "BASE", 1)];
+const HEURES_CREUSES: &[TariffPeriod] = &[
+    period("HC..", "HCHC", "HEURE CREUSE", 1),
+    period("HP..", "HCHP", "HEURE PLEINE", 2),
+];
+const EJP: &[TariffPeriod] = &[
+    period("HN..", "EJPHN", "HEURE NORMALE", 1),
+    period("PM..", "EJPHPM", "HEURE POINTE", 2),
+];
+// the legacy indices list the red days before the white ones, the supplier indices after
+const TEMPO: &[TariffPeriod] = &[
+    period("HCJB", "BBRHCJB", "HC BLEU", 1),
+    period("HPJB", "BBRHPJB", "HP BLEU", 2),
+    period("HCJR", "BBRHCJR", "HC ROUGE", 5),
+    period("HPJR", "BBRHPJR", "HP ROUGE", 6),
+    period("HCJW", "BBRHCJW", "HC BLANC", 3),
+    period("HPJW", "BBRHPJW", "HP BLANC", 4),
+];
+
+/// TariffOption is the tariff option of the supplier contract
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TariffOption {
+    /// a single index
+    Base,
+    /// off-peak and peak hours
+    HeuresCreuses,
+    /// normal and mobile peak days (effacement jour de pointe)
+    Ejp,
+    /// blue, white and red days with off-peak and peak hours, `program` is the program
+    /// character of the legacy OPTARIF (BBRx), None in standard mode
+    Tempo { program: Option<char> },
+}
+
+impl TariffOption {
+    /// Return the tariff option of a legacy OPTARIF value, an UnknownTariff error if it is
+    /// not one of BASE, HC.., EJP. or BBRx
+    /// # Example
+    /// ```
+    /// use teleinfo_nom::TariffOption;
+    /// assert_eq!(TariffOption::from_optarif("HC..").unwrap(), TariffOption::HeuresCreuses);
+    /// assert_eq!(
+    ///     TariffOption::from_optarif("BBR(").unwrap(),
+    ///     TariffOption::Tempo { program: Some('(') }
+    /// );
+    /// assert!(TariffOption::from_optarif("BB").is_err());
+    /// ```
+    pub fn from_optarif(value: &str) -> Result<TariffOption, TeleinfoError> {
+        match value {
+            "BASE" => Ok(TariffOption::Base),
+            "HC.." => Ok(TariffOption::HeuresCreuses),
+            "EJP." => Ok(TariffOption::Ejp),
+            _ => {
+                let mut program = value.strip_prefix("BBR").map(str::chars);
+                match program.as_mut().map(|p| (p.next(), p.next())) {
+                    Some((Some(program), None)) => Ok(TariffOption::Tempo {
+                        program: Some(program),
+                    }),
+                    _ => Err(unknown("OPTARIF", value)),
+                }
+            }
+        }
+    }
+
+    /// Return the tariff option of a standard NGTF value, the supplier calendar name, an
+    /// UnknownTariff error if it names none of the options
+    /// # Example
+    /// ```
+    /// use teleinfo_nom::TariffOption;
+    /// assert_eq!(
+    ///     TariffOption::from_ngtf("     TEMPO      ").unwrap(),
+    ///     TariffOption::Tempo { program: None }
+    /// );
+    /// assert_eq!(
+    ///     TariffOption::from_ngtf("H PLEINE/CREUSE ").unwrap(),
+    ///     TariffOption::HeuresCreuses
+    /// );
+    /// ```
+    pub fn from_ngtf(value: &str) -> Result<TariffOption, TeleinfoError> {
+        let name = value.trim().to_uppercase();
+        if name.contains("TEMPO") || name.starts_with("BBR") {
+            Ok(TariffOption::Tempo { program: None })
+        } else if name.contains("EJP") {
+            Ok(TariffOption::Ejp)
+        } else if name.contains("BASE") {
+            Ok(TariffOption::Base)
+        } else if name.contains("CREUSE") || name.starts_with("HC") {
+            Ok(TariffOption::HeuresCreuses)
+        } else {
+            Err(unknown("NGTF", value))
+        }
+    }
+
+    /// Return the tariff periods of the option, in the order of their legacy indices
+    pub fn periods(&self) -> &'static [TariffPeriod] {
+        match self {
+            TariffOption::Base => BASE,
+            TariffOption::HeuresCreuses => HEURES_CREUSES,
+            TariffOption::Ejp => EJP,
+            TariffOption::Tempo { .. } => TEMPO,
+        }
+    }
+
+    /// Return the labels of the energy indices of the option in legacy mode
+    pub fn legacy_indices(&self) -> Vec<String> {
+        self.periods().iter().map(|p| p.index.to_string()).collect()
+    }
+}
+
+impl TariffPeriod {
+    /// Return the tariff period of a legacy PTEC value, an UnknownTariff error if no option
+    /// has it
+    /// # Example
+    /// ```
+    /// let period = teleinfo_nom::TariffPeriod::from_ptec("HPJB").unwrap();
+    /// assert_eq!(period.index, "BBRHPJB");
+    /// assert_eq!(period.name, "HP BLEU");
+    /// ```
+    pub fn from_ptec(value: &str) -> Result<TariffPeriod, TeleinfoError> {
+        [BASE, HEURES_CREUSES, EJP, TEMPO]
+            .iter()
+            .flat_map(|periods| periods.iter())
+            .find(|p| p.ptec == value)
+            .copied()
+            .ok_or_else(|| unknown("PTEC", value))
+    }
+}
+
+fn unknown(label: &str, value: &str) -> TeleinfoError {
+    TeleinfoError::UnknownTariff {
+        label: label.to_string(),
+        value: value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tariff::*;
+    #[test]
+    fn test_optarif() {
+        assert_eq!(
+            TariffOption::from_optarif("BASE").unwrap(),
+            TariffOption::Base
+        );
+        assert_eq!(
+            TariffOption::from_optarif("EJP.").unwrap(),
+            TariffOption::Ejp
+        );
+        assert_eq!(
+            TariffOption::from_optarif("BBRA").unwrap().legacy_indices(),
+            vec!["BBRHCJB", "BBRHPJB", "BBRHCJR", "BBRHPJR", "BBRHCJW", "BBRHPJW"]
+        );
+        // short and unknown values are reported, not mapped to BASE
+        for value in ["", "B", "BBR", "BBRAB", "HC", "TEMPO"].iter() {
+            match TariffOption::from_optarif(value) {
+                Err(TeleinfoError::UnknownTariff { label, value: v }) => {
+                    assert_eq!(label, "OPTARIF");
+                    assert_eq!(&v, value);
+                }
+                r => panic!("unexpected result {:?} for {:?}", r, value),
+            }
+        }
+    }
+    #[test]
+    fn test_ngtf() {
+        assert_eq!(
+            TariffOption::from_ngtf("      EJP       ").unwrap(),
+            TariffOption::Ejp
+        );
+        assert_eq!(
+            TariffOption::from_ngtf("      BASE      ").unwrap(),
+            TariffOption::Base
+        );
+        assert!(TariffOption::from_ngtf("   PRODUCTEUR   ").is_err());
+        assert!(TariffOption::from_ngtf("").is_err());
+        // the legacy and standard periods of an option match
+        let tempo = TariffOption::from_ngtf("TEMPO").unwrap();
+        for period in tempo.periods() {
+            assert_eq!(TariffPeriod::from_ptec(period.ptec).unwrap(), *period);
+        }
+        let supplier: Vec<_> = tempo.periods().iter().map(|p| p.supplier).collect();
+        assert_eq!(supplier, vec![1, 2, 5, 6, 3, 4]);
+        assert_eq!(TariffPeriod::from_ptec("HCJW").unwrap().name, "HC BLANC");
+        assert!(TariffPeriod::from_ptec("XXXX").is_err());
+    }
+}